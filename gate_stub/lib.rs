@@ -0,0 +1,33 @@
+#![cfg_attr(not(feature = "std"), no_std, no_main)]
+
+/// A `TransferGate` whose answer is toggled by hand, for exercising the token's gate.
+#[ink::contract]
+mod gate_stub {
+  use trait_erc20::TransferGate;
+
+  #[ink(storage)]
+  pub struct GateStub {
+    allowed: bool,
+  }
+
+  impl GateStub {
+    #[ink(constructor)]
+    pub fn new(allowed: bool) -> Self {
+      Self { allowed }
+    }
+
+    #[ink(message)]
+    pub fn set_allowed(&mut self, allowed: bool) {
+      self.allowed = allowed;
+    }
+  }
+
+  impl TransferGate for GateStub {
+    #[ink(message)]
+    fn is_allowed(&self) -> bool {
+      self.allowed
+    }
+  }
+}
+
+pub use gate_stub::{ GateStub, GateStubRef };
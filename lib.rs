@@ -2,171 +2,3777 @@
 
 #[ink::contract]
 mod erc20 {
-  use ink::storage::Mapping;
-  use trait_erc20::{ TERC20, Error, Result };
+  use ink::prelude::vec::Vec;
+  use ink::env::hash::Blake2x256;
+  use ink::storage::{ Lazy, Mapping };
+  use trait_erc20::{ TERC20, TransferGate, Error, Result };
+
+  /// Largest number of entries a batched query accepts.
+  pub const MAX_BATCH_QUERY: usize = 50;
+
+  /// Largest number of accounts `consolidate` sweeps in one call.
+  pub const MAX_CONSOLIDATE: usize = 20;
+
+  /// Largest number of operations one `execute_batch_by_sig` call carries.
+  pub const MAX_SIGNED_OPS: usize = 16;
+
+  /// Number of largest holders kept ranked in storage.
+  pub const MAX_TOP_HOLDERS: usize = 20;
+
+  /// Extra ranked entries kept below `MAX_TOP_HOLDERS` so a listed holder whose balance
+  /// drops can be replaced without reading every holder.
+  pub const TOP_HOLDER_BUFFER: usize = 10;
+
+  /// Length of the year `SupplyCap::growth_per_year` is measured over, in milliseconds.
+  pub const MILLIS_PER_YEAR: u64 = 365 * 24 * 60 * 60 * 1000;
+
+  /// Distinct approvers needed before a mint above the threshold can execute.
+  pub const MINT_APPROVALS_REQUIRED: usize = 2;
+
+  /// Most mints that can wait in the public mint queue at once.
+  pub const MAX_QUEUED_MINTS: usize = 20;
+
     /// Defines the storage of your contract.
     /// Add new fields to the below struct in order
     /// to add new static storage fields to your contract.
     #[ink(storage)]
-    #[derive(Default)]
     pub struct Erc20 {
       total_supply: Balance,
       balances: Mapping<AccountId, Balance>,
-      allowances: Mapping<(AccountId, AccountId), Balance>
+      /// Accounts with a nonzero balance, indexed `0..holder_count`.
+      holders: Mapping<u32, AccountId>,
+      holder_index: Mapping<AccountId, u32>,
+      holder_count: u32,
+      /// The `MAX_TOP_HOLDERS + TOP_HOLDER_BUFFER` largest balances known, largest first.
+      top_holders: Lazy<Vec<(AccountId, Balance)>>,
+      /// Set the first time an account holds tokens and never cleared.
+      ever_held: Mapping<AccountId, bool>,
+      /// Receives the rounding remainder of `distribute_proportional` when set.
+      distribution_sink: Option<AccountId>,
+      /// Revenue held by the contract for the next scheduled distribution.
+      pending_revenue: Balance,
+      distribution_interval_secs: u64,
+      last_distribution_at: Timestamp,
+      allowances: Mapping<(AccountId, AccountId), Balance>,
+      /// Every spender an owner has approved, so allowances can be enumerated.
+      spenders: Mapping<AccountId, Vec<AccountId>>,
+      /// Allowances expressed in basis points of the owner's live balance.
+      percent_allowances: Mapping<(AccountId, AccountId), u16>,
+      recurring_allowances: Mapping<(AccountId, AccountId), RecurringAllowance>,
+      allowance_updated_at: Mapping<(AccountId, AccountId), Timestamp>,
+      /// When an allowance stops being usable, for approvals made under a TTL.
+      allowance_expiry: Mapping<(AccountId, AccountId), Timestamp>,
+      /// Lifetime, in seconds, given to every new approval.
+      default_allowance_ttl: Option<u64>,
+      /// Sum of all fixed allowances granted to a spender, across owners.
+      total_allowance_to: Mapping<AccountId, Balance>,
+      owner: AccountId,
+      /// Upper bound on any single allowance, set by the owner.
+      max_allowance_per_spender: Option<Balance>,
+      delayed_transfers: Mapping<u64, DelayedTransfer>,
+      next_delayed_transfer_id: u64,
+      claimable_transfers: Mapping<u64, ClaimableTransfer>,
+      next_claimable_transfer_id: u64,
+      /// Tokens held in delayed transfers, claimable transfers and burn proposals.
+      escrowed: Balance,
+      /// Plain `transfer`s above this value must go through `transfer_with_memo`.
+      memo_required_above: Option<Balance>,
+      emission: Option<EmissionSchedule>,
+      pause_level: PauseLevel,
+      /// Whether the current `pause_level` was set by the circuit breaker.
+      breaker_tripped: bool,
+      /// Outflow within one window that trips the circuit breaker; `None` disables it.
+      breaker_threshold: Option<Balance>,
+      breaker_window_blocks: BlockNumber,
+      breaker_window_start: BlockNumber,
+      /// Sum of every account's net outflow in the current window.
+      breaker_outflow: Balance,
+      breaker_flows: Mapping<AccountId, BreakerFlow>,
+      sent_count: Mapping<AccountId, u64>,
+      received_count: Mapping<AccountId, u64>,
+      /// Mints above this value need `MINT_APPROVALS_REQUIRED` approvers.
+      mint_approval_threshold: Option<Balance>,
+      mint_approvers: Lazy<Vec<AccountId>>,
+      mint_proposals: Mapping<u64, MintProposal>,
+      next_mint_proposal_id: u64,
+      /// Minimum seconds between two owner mints.
+      min_mint_interval_secs: Option<u64>,
+      last_mint_at: Option<Timestamp>,
+      /// Announced mints, readable by anyone, that execute once their delay has passed.
+      mint_queue: Lazy<Vec<PendingMint>>,
+      mint_queue_delay_secs: u64,
+      /// When false the token is soulbound: only minting and burning move it.
+      transferable: bool,
+      /// When set, transfers may only be sent to registered accounts.
+      require_registration: bool,
+      registered: Mapping<AccountId, ()>,
+      burn_proposals: Mapping<u64, BurnProposal>,
+      next_burn_proposal_id: u64,
+      /// Combined veto weight that cancels a burn proposal.
+      burn_veto_quorum: Balance,
+      burn_delay_secs: u64,
+      /// Balance each vetoer must keep, and until when, so it cannot veto twice.
+      veto_locks: Mapping<AccountId, (Balance, Timestamp)>,
+      /// Share of each transfer, in basis points, locked into the recipient's savings.
+      forced_savings_bps: u16,
+      savings_lock_secs: u64,
+      savings: Mapping<AccountId, Savings>,
+      savings_total: Balance,
+      /// Contract asked through `TransferGate::is_allowed` before every transfer.
+      gate_oracle: Option<AccountId>,
+      /// Approvals above this are staged until the owner calls `confirm_large_approval`.
+      large_approval_threshold: Option<Balance>,
+      pending_large_approvals: Mapping<(AccountId, AccountId), Balance>,
+      /// Lifetime cap on the transfers an account may send, counted by `sent_count`.
+      max_transfers_per_account: Option<u32>,
+      supply_cap: Option<SupplyCap>,
+      /// Next nonce each account must sign for `execute_batch_by_sig`.
+      sig_nonces: Mapping<AccountId, u64>,
+    }
+
+    /// Tokens escrowed by `transfer_delayed` until `release_at`.
+    #[derive(Debug, PartialEq, Eq, Clone, scale::Encode, scale::Decode)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout))]
+    pub struct DelayedTransfer {
+      pub from: AccountId,
+      pub to: AccountId,
+      pub value: Balance,
+      pub release_at: Timestamp,
+    }
+
+    /// Tokens escrowed by `transfer_claimable`; `to` must claim them before `expiry`.
+    #[derive(Debug, PartialEq, Eq, Clone, scale::Encode, scale::Decode)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout))]
+    pub struct ClaimableTransfer {
+      pub from: AccountId,
+      pub to: AccountId,
+      pub value: Balance,
+      pub expiry: Timestamp,
+    }
+
+    /// Linear emission of `per_sec` tokens to `recipient` until `end`.
+    #[derive(Debug, PartialEq, Eq, Clone, scale::Encode, scale::Decode)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout))]
+    pub struct EmissionSchedule {
+      pub recipient: AccountId,
+      pub per_sec: Balance,
+      pub end: Timestamp,
+      /// Everything up to this timestamp has already been minted.
+      pub last_emitted: Timestamp,
+    }
+
+    /// A large mint waiting for approvals.
+    #[derive(Debug, PartialEq, Eq, Clone, scale::Encode, scale::Decode)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout))]
+    pub struct MintProposal {
+      pub to: AccountId,
+      pub value: Balance,
+      pub approvals: Vec<AccountId>,
+    }
+
+    /// What an account sent and received within the breaker window starting at `window_start`.
+    #[derive(Debug, Default, PartialEq, Eq, Clone, scale::Encode, scale::Decode)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout))]
+    pub struct BreakerFlow {
+      pub window_start: BlockNumber,
+      pub sent: Balance,
+      pub received: Balance,
+    }
+
+    /// A mint announced through `queue_mint` that may execute from `ready_at`.
+    #[derive(Debug, PartialEq, Eq, Clone, scale::Encode, scale::Decode)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout))]
+    pub struct PendingMint {
+      pub to: AccountId,
+      pub value: Balance,
+      pub ready_at: Timestamp,
+    }
+
+    /// An allowance of `per_period` that refills every `period_secs`, counted from `anchor`.
+    #[derive(Debug, PartialEq, Eq, Clone, scale::Encode, scale::Decode)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout))]
+    pub struct RecurringAllowance {
+      pub per_period: Balance,
+      pub period_secs: u64,
+      pub anchor: Timestamp,
+      pub period: u64,
+      pub spent: Balance,
+    }
+
+    /// A ceiling on `total_supply` of `base` at `start`, rising by `growth_per_year`.
+    #[derive(Debug, PartialEq, Eq, Clone, scale::Encode, scale::Decode)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout))]
+    pub struct SupplyCap {
+      pub base: Balance,
+      pub growth_per_year: Balance,
+      pub start: Timestamp,
+    }
+
+    /// Tokens held by the contract for an account, in an older tranche unlocking at
+    /// `unlock_at` and a newer one unlocking at `newer_unlock_at`. A deposit never moves
+    /// `unlock_at`, but it restarts the newer tranche's lock, so a stream of deposits can
+    /// keep the newer tranche locked while the older one unlocks on schedule.
+    #[derive(Debug, Default, PartialEq, Eq, Clone, scale::Encode, scale::Decode)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout))]
+    pub struct Savings {
+      pub amount: Balance,
+      pub unlock_at: Timestamp,
+      pub newer_amount: Balance,
+      pub newer_unlock_at: Timestamp,
+    }
+
+    /// Tokens escrowed for burning at `finalize_at` unless holders veto it first.
+    #[derive(Debug, PartialEq, Eq, Clone, scale::Encode, scale::Decode)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout))]
+    pub struct BurnProposal {
+      pub proposer: AccountId,
+      pub value: Balance,
+      pub finalize_at: Timestamp,
+      pub veto_weight: Balance,
+      pub vetoers: Vec<AccountId>,
+    }
+
+    /// One action in a batch signed for `execute_batch_by_sig`, performed as the signer.
+    #[derive(Debug, PartialEq, Eq, Clone, scale::Encode, scale::Decode)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+    pub enum Op {
+      Transfer { to: AccountId, value: Balance },
+      Approve { spender: AccountId, value: Balance },
+      Burn { value: Balance },
+    }
+
+    /// How much of the token is frozen. `TransfersOnly` still lets holders burn and
+    /// take tokens out of the contract's escrows; `Full` stops both.
+    #[derive(Debug, PartialEq, Eq, Clone, Copy, scale::Encode, scale::Decode)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout))]
+    pub enum PauseLevel {
+      None,
+      TransfersOnly,
+      Full,
+    }
+
+    /// Privileged actions that `can_execute` reports on.
+    #[derive(Debug, PartialEq, Eq, Clone, Copy, scale::Encode, scale::Decode)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+    pub enum ActionKind {
+      Mint,
+      ProposeMint,
+      ApproveMint,
+      ExecuteMint,
+      QueueMint,
+      CancelQueuedMint,
+      SetMintQueueDelay,
+      SetApprovers,
+      SetMintApprovalThreshold,
+      SetMinMintInterval,
+      SetEmissionSchedule,
+      SetCircuitBreaker,
+      ResetCircuitBreaker,
+      SetTransferable,
+      SetRequireRegistration,
+      Register,
+      Deregister,
+      SetMaxAllowancePerSpender,
+      SetDefaultAllowanceTtl,
+      SetMemoRequiredAbove,
+      SetDistributionSink,
+      SetDistributionInterval,
+      ProposeBurn,
+      SetBurnGovernance,
+      SetForcedSavings,
+      SetGateOracle,
+      SetLargeApprovalThreshold,
+      Consolidate,
+      SetMaxTransfersPerAccount,
+      SetSupplyCap,
+      SetPauseLevel,
+      RenounceOwnership,
+    }
+
+    /// Every owner-tunable setting, as returned by `config`.
+    #[derive(Debug, PartialEq, Eq, Clone, scale::Encode, scale::Decode)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+    pub struct Config {
+      pub owner: AccountId,
+      pub pause_level: PauseLevel,
+      pub max_allowance_per_spender: Option<Balance>,
+      pub memo_required_above: Option<Balance>,
+      pub distribution_sink: Option<AccountId>,
+      pub emission: Option<EmissionSchedule>,
+      pub breaker_threshold: Option<Balance>,
+      pub breaker_window_blocks: BlockNumber,
+      pub mint_approval_threshold: Option<Balance>,
+      pub mint_approvers: Vec<AccountId>,
+      pub default_allowance_ttl: Option<u64>,
+      pub min_mint_interval_secs: Option<u64>,
+      pub mint_queue_delay_secs: u64,
+      pub transferable: bool,
+      pub require_registration: bool,
+      pub distribution_interval_secs: u64,
+      pub burn_veto_quorum: Balance,
+      pub burn_delay_secs: u64,
+      pub forced_savings_bps: u16,
+      pub savings_lock_secs: u64,
+      pub gate_oracle: Option<AccountId>,
+      pub large_approval_threshold: Option<Balance>,
+      pub max_transfers_per_account: Option<u32>,
+      pub supply_cap: Option<SupplyCap>,
+    }
+
+    #[ink(event)]
+    pub struct Transfer {
+      from: Option<AccountId>,
+      to: Option<AccountId>,
+      value: Balance
+    }
+
+    #[ink(event)]
+    pub struct Approve {
+      from: AccountId,
+      to: AccountId,
+      value: Balance,
+    }
+
+    #[ink(event)]
+    pub struct PercentApproval {
+      from: AccountId,
+      to: AccountId,
+      bps: u16,
+    }
+
+    #[ink(event)]
+    pub struct TransferMemo {
+      from: AccountId,
+      to: AccountId,
+      value: Balance,
+      memo: Vec<u8>,
+    }
+
+    #[ink(event)]
+    pub struct CircuitBreakerTripped {
+      outflow: Balance,
+      block: BlockNumber,
+    }
+
+    #[ink(event)]
+    pub struct OwnershipRenounced {
+      previous_owner: AccountId,
     }
 
+    impl Erc20 {
+      /// Constructor that initializes the `bool` value to the given `init_value`.
+      #[ink(constructor)]
+      pub fn new(total_supply: Balance) -> Self {
+        Self::env().emit_event(Transfer{
+          from: None,
+          to: Some(Self::env().caller()),
+          value: total_supply,
+        });
+        let mut instance = Self {
+          total_supply,
+          balances: Mapping::new(),
+          holders: Mapping::new(),
+          holder_index: Mapping::new(),
+          holder_count: 0,
+          top_holders: Lazy::new(),
+          ever_held: Mapping::new(),
+          distribution_sink: None,
+          pending_revenue: 0,
+          distribution_interval_secs: 0,
+          last_distribution_at: 0,
+          allowances: Mapping::new(),
+          spenders: Mapping::new(),
+          percent_allowances: Mapping::new(),
+          recurring_allowances: Mapping::new(),
+          allowance_updated_at: Mapping::new(),
+          allowance_expiry: Mapping::new(),
+          default_allowance_ttl: None,
+          total_allowance_to: Mapping::new(),
+          owner: Self::env().caller(),
+          max_allowance_per_spender: None,
+          delayed_transfers: Mapping::new(),
+          next_delayed_transfer_id: 0,
+          claimable_transfers: Mapping::new(),
+          next_claimable_transfer_id: 0,
+          escrowed: 0,
+          memo_required_above: None,
+          emission: None,
+          pause_level: PauseLevel::None,
+          breaker_tripped: false,
+          breaker_threshold: None,
+          breaker_window_blocks: 0,
+          breaker_window_start: 0,
+          breaker_outflow: 0,
+          breaker_flows: Mapping::new(),
+          sent_count: Mapping::new(),
+          received_count: Mapping::new(),
+          mint_approval_threshold: None,
+          mint_approvers: Lazy::new(),
+          mint_proposals: Mapping::new(),
+          next_mint_proposal_id: 0,
+          min_mint_interval_secs: None,
+          last_mint_at: None,
+          mint_queue: Lazy::new(),
+          mint_queue_delay_secs: 0,
+          transferable: true,
+          require_registration: false,
+          registered: Mapping::new(),
+          burn_proposals: Mapping::new(),
+          next_burn_proposal_id: 0,
+          burn_veto_quorum: 0,
+          burn_delay_secs: 0,
+          veto_locks: Mapping::new(),
+          forced_savings_bps: 0,
+          savings_lock_secs: 0,
+          savings: Mapping::new(),
+          savings_total: 0,
+          gate_oracle: None,
+          large_approval_threshold: None,
+          pending_large_approvals: Mapping::new(),
+          max_transfers_per_account: None,
+          supply_cap: None,
+          sig_nonces: Mapping::new(),
+        };
+        instance.set_balance(&Self::env().caller(), total_supply);
+        instance
+      }
+
+      #[ink(message)]
+      pub fn config(&self) -> Config {
+        Config {
+          owner: self.owner,
+          pause_level: self.pause_level(),
+          max_allowance_per_spender: self.max_allowance_per_spender,
+          memo_required_above: self.memo_required_above,
+          distribution_sink: self.distribution_sink,
+          emission: self.emission.clone(),
+          breaker_threshold: self.breaker_threshold,
+          breaker_window_blocks: self.breaker_window_blocks,
+          mint_approval_threshold: self.mint_approval_threshold,
+          mint_approvers: self.mint_approvers.get_or_default(),
+          default_allowance_ttl: self.default_allowance_ttl,
+          min_mint_interval_secs: self.min_mint_interval_secs,
+          mint_queue_delay_secs: self.mint_queue_delay_secs,
+          transferable: self.transferable,
+          require_registration: self.require_registration,
+          distribution_interval_secs: self.distribution_interval_secs,
+          burn_veto_quorum: self.burn_veto_quorum,
+          burn_delay_secs: self.burn_delay_secs,
+          forced_savings_bps: self.forced_savings_bps,
+          savings_lock_secs: self.savings_lock_secs,
+          gate_oracle: self.gate_oracle,
+          large_approval_threshold: self.large_approval_threshold,
+          max_transfers_per_account: self.max_transfers_per_account,
+          supply_cap: self.supply_cap.clone(),
+        }
+      }
+
+      /// Whether the caller is currently allowed to perform `action`.
+      #[ink(message)]
+      pub fn can_execute(&self, action: ActionKind) -> bool {
+        match action {
+          ActionKind::ApproveMint => self.mint_approvers.get_or_default().contains(&self.env().caller()),
+          _ => self.ensure_owner().is_ok(),
+        }
+      }
+
+      #[ink(message)]
+      pub fn owner(&self) -> AccountId {
+        self.owner
+      }
+
+      #[ink(message)]
+      pub fn max_allowance_per_spender(&self) -> Option<Balance> {
+        self.max_allowance_per_spender
+      }
+
+      /// Caps the allowance any account may grant a single spender; `None` removes the cap.
+      #[ink(message)]
+      pub fn set_max_allowance_per_spender(&mut self, ceiling: Option<Balance>) -> Result<()> {
+        self.ensure_owner()?;
+        self.max_allowance_per_spender = ceiling;
+        Ok(())
+      }
+
+      /// Permanently gives up ownership; every owner-gated message fails afterwards.
+      #[ink(message)]
+      pub fn renounce_ownership(&mut self) -> Result<()> {
+        self.ensure_owner()?;
+        let previous_owner = self.owner;
+        self.owner = AccountId::from([0x0; 32]);
+
+        self.env().emit_event(OwnershipRenounced { previous_owner });
+
+        Ok(())
+      }
+
+      /// Escrows `value` in the contract; the sender may cancel it for `delay_secs`,
+      /// after which anyone may release it to `to` with `claim_transfer`. `to` may not be
+      /// the contract itself.
+      #[ink(message)]
+      pub fn transfer_delayed(&mut self, to: AccountId, value: Balance, delay_secs: u64) -> Result<u64> {
+        let contract = self.env().account_id();
+        if to == contract {
+          return Err(Error::NotAllowed);
+        }
+        self.ensure_registered(&to)?;
+        let sender = self.env().caller();
+        self.transfer_helper(&sender, &contract, value)?;
+        self.escrowed += value;
+
+        let id = self.next_delayed_transfer_id;
+        let release_at = self.env().block_timestamp().saturating_add(delay_secs.saturating_mul(1000));
+        self.delayed_transfers.insert(id, &DelayedTransfer { from: sender, to, value, release_at });
+        self.next_delayed_transfer_id += 1;
+
+        Ok(id)
+      }
+
+      #[ink(message)]
+      pub fn cancel_transfer(&mut self, id: u64) -> Result<()> {
+        let pending = self.delayed_transfers.get(id).ok_or(Error::DelayedTransferNotFound)?;
+        if self.env().caller() != pending.from {
+          return Err(Error::NotAllowed);
+        }
+        if self.env().block_timestamp() >= pending.release_at {
+          return Err(Error::CancelWindowElapsed);
+        }
+
+        self.delayed_transfers.remove(id);
+        let contract = self.env().account_id();
+        self.escrowed -= pending.value;
+        self.transfer_helper(&contract, &pending.from, pending.value)
+      }
+
+      #[ink(message)]
+      pub fn claim_transfer(&mut self, id: u64) -> Result<()> {
+        let pending = self.delayed_transfers.get(id).ok_or(Error::DelayedTransferNotFound)?;
+        if self.env().block_timestamp() < pending.release_at {
+          return Err(Error::TransferStillPending);
+        }
+
+        self.delayed_transfers.remove(id);
+        let contract = self.env().account_id();
+        self.escrowed -= pending.value;
+        self.transfer_helper(&contract, &pending.to, pending.value)
+      }
+
+      /// Mints `value` new tokens to `to`. Amounts above the mint approval threshold
+      /// must go through `propose_mint` instead.
+      #[ink(message)]
+      pub fn mint(&mut self, to: AccountId, value: Balance) -> Result<()> {
+        self.ensure_owner()?;
+        if let Some(threshold) = self.mint_approval_threshold {
+          if value > threshold {
+            return Err(Error::MintRequiresApproval);
+          }
+        }
+        self.throttle_mint()?;
+        self.mint_to(&to, value)
+      }
+
+      /// Bounds every mint by a cap of `base_cap` that grows by `growth_per_year`,
+      /// pro rata, from now on; `None` removes the cap.
+      #[ink(message)]
+      pub fn set_supply_cap(&mut self, base_cap: Option<Balance>, growth_per_year: Balance) -> Result<()> {
+        self.ensure_owner()?;
+        self.supply_cap = base_cap.map(|base| SupplyCap {
+          base,
+          growth_per_year,
+          start: self.env().block_timestamp(),
+        });
+        Ok(())
+      }
+
+      /// The most `total_supply` may reach right now, if capped.
+      #[ink(message)]
+      pub fn current_cap(&self) -> Option<Balance> {
+        self.cap_at(self.env().block_timestamp())
+      }
+
+      fn cap_at(&self, timestamp: Timestamp) -> Option<Balance> {
+        self.supply_cap.as_ref().map(|cap| {
+          let elapsed = timestamp.saturating_sub(cap.start);
+          let growth = cap.growth_per_year.saturating_mul(elapsed as Balance) / MILLIS_PER_YEAR as Balance;
+          cap.base.saturating_add(growth)
+        })
+      }
+
+      /// Destroys `value` of the caller's tokens.
+      #[ink(message)]
+      pub fn burn(&mut self, value: Balance) -> Result<()> {
+        let caller = self.env().caller();
+        self.burn_from(&caller, value)
+      }
+
+      #[ink(message)]
+      pub fn set_burn_governance(&mut self, veto_quorum: Balance, delay_secs: u64) -> Result<()> {
+        self.ensure_owner()?;
+        if veto_quorum == 0 {
+          return Err(Error::InvalidQuorum);
+        }
+        self.burn_veto_quorum = veto_quorum;
+        self.burn_delay_secs = delay_secs;
+        Ok(())
+      }
+
+      /// Escrows `value` of the owner's tokens to be burned after `burn_delay_secs`
+      /// unless holders veto it first.
+      #[ink(message)]
+      pub fn propose_burn(&mut self, value: Balance) -> Result<u64> {
+        self.ensure_owner()?;
+        let proposer = self.env().caller();
+        let contract = self.env().account_id();
+        self.transfer_helper(&proposer, &contract, value)?;
+        self.escrowed += value;
+
+        let id = self.next_burn_proposal_id;
+        let finalize_at = self.env().block_timestamp().saturating_add(self.burn_delay_secs.saturating_mul(1000));
+        self.burn_proposals.insert(id, &BurnProposal {
+          proposer,
+          value,
+          finalize_at,
+          veto_weight: 0,
+          vetoers: Vec::new(),
+        });
+        self.next_burn_proposal_id += 1;
+
+        Ok(id)
+      }
+
+      /// Locks `bps` / 10000 of every user transfer into the recipient's savings for
+      /// `lock_secs`. See `Savings` for how deposits share the two lock tranches.
+      #[ink(message)]
+      pub fn set_forced_savings(&mut self, bps: u16, lock_secs: u64) -> Result<()> {
+        self.ensure_owner()?;
+        if bps > 10000 {
+          return Err(Error::InvalidBasisPoints);
+        }
+        self.forced_savings_bps = bps;
+        self.savings_lock_secs = lock_secs;
+        Ok(())
+      }
+
+      /// Blocks transfers with `GateClosed` whenever `oracle` reports `is_allowed() == false`.
+      /// The call is made without reentry, so the oracle cannot call back into the token.
+      #[ink(message)]
+      pub fn set_gate_oracle(&mut self, oracle: Option<AccountId>) -> Result<()> {
+        self.ensure_owner()?;
+        self.gate_oracle = oracle;
+        Ok(())
+      }
+
+      #[ink(message)]
+      pub fn gate_oracle(&self) -> Option<AccountId> {
+        self.gate_oracle
+      }
+
+      #[ink(message)]
+      pub fn savings_of(&self, account: AccountId) -> Balance {
+        self.savings.get(account).map_or(0, |savings| savings.amount + savings.newer_amount)
+      }
+
+      #[ink(message)]
+      pub fn savings(&self, account: AccountId) -> Option<Savings> {
+        self.savings.get(account)
+      }
+
+      /// When the caller's older savings tranche unlocks.
+      #[ink(message)]
+      pub fn savings_unlock_at(&self, account: AccountId) -> Option<Timestamp> {
+        self.savings.get(account).map(|savings| savings.unlock_at)
+      }
+
+      /// Releases every savings tranche of the caller's that has unlocked.
+      #[ink(message)]
+      pub fn withdraw_savings(&mut self) -> Result<()> {
+        let caller = self.env().caller();
+        let mut savings = self.savings.get(caller).ok_or(Error::BalanceTooLow)?;
+        let now = self.env().block_timestamp();
+
+        let mut released = 0;
+        while savings.amount > 0 && now >= savings.unlock_at {
+          released += savings.amount;
+          savings.amount = savings.newer_amount;
+          savings.unlock_at = savings.newer_unlock_at;
+          savings.newer_amount = 0;
+        }
+        if released == 0 {
+          return Err(Error::SavingsLocked);
+        }
+
+        if savings.amount == 0 {
+          self.savings.remove(caller);
+        } else {
+          self.savings.insert(caller, &savings);
+        }
+        let contract = self.env().account_id();
+        self.savings_total -= released;
+        self.transfer_helper(&contract, &caller, released)
+      }
+
+      #[ink(message)]
+      pub fn burn_proposal(&self, id: u64) -> Option<BurnProposal> {
+        self.burn_proposals.get(id)
+      }
+
+      /// Adds the caller's current balance to the veto weight against burn `id`. Once
+      /// the weight reaches `burn_veto_quorum` the burn is cancelled and refunded.
+      ///
+      /// That much of the caller's balance stays locked until the veto window closes,
+      /// so the same tokens cannot veto again from another account.
+      #[ink(message)]
+      pub fn veto_burn(&mut self, id: u64) -> Result<()> {
+        let caller = self.env().caller();
+        let mut proposal = self.burn_proposals.get(id).ok_or(Error::BurnProposalNotFound)?;
+        if self.env().block_timestamp() >= proposal.finalize_at {
+          return Err(Error::VetoWindowClosed);
+        }
+        if proposal.vetoers.contains(&caller) {
+          return Err(Error::AlreadyVetoed);
+        }
+
+        let weight = self.balance_of(caller);
+        if weight == 0 {
+          return Err(Error::BalanceTooLow);
+        }
+
+        let (locked, until) = self.veto_locks.get(caller).unwrap_or_default();
+        self.veto_locks.insert(caller, &(weight.max(locked), proposal.finalize_at.max(until)));
+        proposal.vetoers.push(caller);
+        proposal.veto_weight = proposal.veto_weight.saturating_add(weight);
+        if proposal.veto_weight < self.burn_veto_quorum {
+          self.burn_proposals.insert(id, &proposal);
+          return Ok(());
+        }
+
+        self.burn_proposals.remove(id);
+        let contract = self.env().account_id();
+        self.escrowed -= proposal.value;
+        self.transfer_helper(&contract, &proposal.proposer, proposal.value)
+      }
+
+      /// Part of `account`'s balance held back by its burn vetoes.
+      #[ink(message)]
+      pub fn veto_locked(&self, account: AccountId) -> Balance {
+        match self.veto_locks.get(account) {
+          Some((locked, until)) if self.env().block_timestamp() < until => locked,
+          _ => 0,
+        }
+      }
+
+      #[ink(message)]
+      pub fn finalize_burn(&mut self, id: u64) -> Result<()> {
+        let proposal = self.burn_proposals.get(id).ok_or(Error::BurnProposalNotFound)?;
+        if self.env().block_timestamp() < proposal.finalize_at {
+          return Err(Error::BurnStillPending);
+        }
+
+        self.burn_proposals.remove(id);
+        let contract = self.env().account_id();
+        self.escrowed -= proposal.value;
+        self.burn_from(&contract, proposal.value)
+      }
+
+      #[ink(message)]
+      pub fn transferable(&self) -> bool {
+        self.transferable
+      }
+
+      /// Turns soulbound mode on (`false`) or off. While soulbound every movement
+      /// between accounts fails; only `mint`, `burn` and payouts of tokens the contract
+      /// already holds, such as escrow refunds and savings, change balances.
+      #[ink(message)]
+      pub fn set_transferable(&mut self, transferable: bool) -> Result<()> {
+        self.ensure_owner()?;
+        self.transferable = transferable;
+        Ok(())
+      }
+
+      #[ink(message)]
+      pub fn set_require_registration(&mut self, required: bool) -> Result<()> {
+        self.ensure_owner()?;
+        self.require_registration = required;
+        Ok(())
+      }
+
+      /// Registers `account` as a recipient. Registration stays until the owner
+      /// calls `deregister`.
+      #[ink(message)]
+      pub fn register(&mut self, account: AccountId) -> Result<()> {
+        self.ensure_owner()?;
+        self.registered.insert(account, &());
+        Ok(())
+      }
+
+      #[ink(message)]
+      pub fn deregister(&mut self, account: AccountId) -> Result<()> {
+        self.ensure_owner()?;
+        self.registered.remove(account);
+        Ok(())
+      }
+
+      #[ink(message)]
+      pub fn is_registered(&self, account: AccountId) -> bool {
+        self.registered.contains(account)
+      }
+
+      #[ink(message)]
+      pub fn set_min_mint_interval(&mut self, interval_secs: Option<u64>) -> Result<()> {
+        self.ensure_owner()?;
+        self.min_mint_interval_secs = interval_secs;
+        Ok(())
+      }
+
+      #[ink(message)]
+      pub fn set_mint_approval_threshold(&mut self, threshold: Option<Balance>) -> Result<()> {
+        self.ensure_owner()?;
+        self.mint_approval_threshold = threshold;
+        Ok(())
+      }
+
+      #[ink(message)]
+      pub fn set_approvers(&mut self, approvers: Vec<AccountId>) -> Result<()> {
+        self.ensure_owner()?;
+        self.mint_approvers.set(&approvers);
+        Ok(())
+      }
+
+      #[ink(message)]
+      pub fn approvers(&self) -> Vec<AccountId> {
+        self.mint_approvers.get_or_default()
+      }
+
+      #[ink(message)]
+      pub fn propose_mint(&mut self, to: AccountId, value: Balance) -> Result<u64> {
+        self.ensure_owner()?;
+        let id = self.next_mint_proposal_id;
+        self.mint_proposals.insert(id, &MintProposal { to, value, approvals: Vec::new() });
+        self.next_mint_proposal_id += 1;
+        Ok(id)
+      }
+
+      #[ink(message)]
+      pub fn mint_proposal(&self, id: u64) -> Option<MintProposal> {
+        self.mint_proposals.get(id)
+      }
+
+      #[ink(message)]
+      pub fn approve_mint(&mut self, id: u64) -> Result<()> {
+        let caller = self.env().caller();
+        if !self.mint_approvers.get_or_default().contains(&caller) {
+          return Err(Error::NotApprover);
+        }
+
+        let mut proposal = self.mint_proposals.get(id).ok_or(Error::ProposalNotFound)?;
+        if proposal.approvals.contains(&caller) {
+          return Err(Error::AlreadyApproved);
+        }
+        proposal.approvals.push(caller);
+        self.mint_proposals.insert(id, &proposal);
+
+        Ok(())
+      }
+
+      /// Executes a proposal once enough current approvers signed off; approvals from
+      /// accounts since dropped from the approver set no longer count.
+      #[ink(message)]
+      pub fn execute_mint(&mut self, id: u64) -> Result<()> {
+        self.ensure_owner()?;
+        let proposal = self.mint_proposals.get(id).ok_or(Error::ProposalNotFound)?;
+        let approvers = self.mint_approvers.get_or_default();
+        let approvals = proposal.approvals.iter()
+          .filter(|approver| approvers.contains(approver))
+          .count();
+        if approvals < MINT_APPROVALS_REQUIRED {
+          return Err(Error::InsufficientApprovals);
+        }
+
+        self.throttle_mint()?;
+        self.mint_proposals.remove(id);
+        self.mint_to(&proposal.to, proposal.value)
+      }
+
+      #[ink(message)]
+      pub fn set_mint_queue_delay(&mut self, delay_secs: u64) -> Result<()> {
+        self.ensure_owner()?;
+        self.mint_queue_delay_secs = delay_secs;
+        Ok(())
+      }
+
+      /// Announces a mint of `value` to `to` that executes `mint_queue_delay_secs` from
+      /// now, and no sooner than `min_mint_interval_secs` after the previous owner mint.
+      /// Amounts above the mint approval threshold must still go through `propose_mint`.
+      #[ink(message)]
+      pub fn queue_mint(&mut self, to: AccountId, value: Balance) -> Result<()> {
+        self.ensure_owner()?;
+        if let Some(threshold) = self.mint_approval_threshold {
+          if value > threshold {
+            return Err(Error::MintRequiresApproval);
+          }
+        }
+        let mut queue = self.mint_queue.get_or_default();
+        if queue.len() >= MAX_QUEUED_MINTS {
+          return Err(Error::BatchTooLarge);
+        }
+
+        let ready_at = self.env().block_timestamp().saturating_add(self.mint_queue_delay_secs.saturating_mul(1000));
+        queue.push(PendingMint { to, value, ready_at });
+        self.mint_queue.set(&queue);
+        Ok(())
+      }
+
+      #[ink(message)]
+      pub fn pending_mints(&self) -> Vec<PendingMint> {
+        self.mint_queue.get_or_default()
+      }
+
+      /// Executes every queued mint whose delay has passed and returns how many ran.
+      /// Anyone may call it. A mint that is too soon after the previous one or would
+      /// exceed the supply cap stays queued for a later call.
+      #[ink(message)]
+      pub fn execute_queued_mints(&mut self) -> Result<u32> {
+        let now = self.env().block_timestamp();
+        let mut executed = 0;
+        let mut waiting = Vec::new();
+        for pending in self.mint_queue.get_or_default() {
+          if pending.ready_at > now || self.ensure_mint_interval().is_err() {
+            waiting.push(pending);
+            continue;
+          }
+          match self.mint_to(&pending.to, pending.value) {
+            Ok(()) => {
+              self.last_mint_at = Some(now);
+              executed += 1;
+            }
+            Err(_) => waiting.push(pending),
+          }
+        }
+        self.mint_queue.set(&waiting);
+
+        Ok(executed)
+      }
+
+      /// Drops the queued mint at `index` in `pending_mints`.
+      #[ink(message)]
+      pub fn cancel_queued_mint(&mut self, index: u32) -> Result<()> {
+        self.ensure_owner()?;
+        let mut queue = self.mint_queue.get_or_default();
+        if index as usize >= queue.len() {
+          return Err(Error::QueuedMintNotFound);
+        }
+        queue.remove(index as usize);
+        self.mint_queue.set(&queue);
+        Ok(())
+      }
+
+      /// Starts a linear emission of `emission_per_sec` tokens to `recipient` until
+      /// `emission_end`. Anything accrued under the previous schedule is minted first.
+      /// A schedule whose total exceeds the mint approval threshold is rejected.
+      #[ink(message)]
+      pub fn set_emission_schedule(&mut self, recipient: AccountId, emission_per_sec: Balance, emission_end: Timestamp) -> Result<()> {
+        self.ensure_owner()?;
+        if let Some(threshold) = self.mint_approval_threshold {
+          let duration_secs = emission_end.saturating_sub(self.env().block_timestamp()) / 1000;
+          if emission_per_sec.saturating_mul(duration_secs as Balance) > threshold {
+            return Err(Error::MintRequiresApproval);
+          }
+        }
+        self.emit_tokens()?;
+        self.emission = Some(EmissionSchedule {
+          recipient,
+          per_sec: emission_per_sec,
+          end: emission_end,
+          last_emitted: self.env().block_timestamp(),
+        });
+        Ok(())
+      }
+
+      #[ink(message)]
+      pub fn emission_schedule(&self) -> Option<EmissionSchedule> {
+        self.emission.clone()
+      }
+
+      /// Mints whatever the schedule has accrued since the last call to its recipient.
+      /// Anyone may trigger it; the recipient is fixed by the owner's schedule. Emission
+      /// beyond the room left under `current_cap` is dropped rather than minted.
+      #[ink(message)]
+      pub fn emit_tokens(&mut self) -> Result<Balance> {
+        let mut schedule = match self.emission.clone() {
+          Some(schedule) => schedule,
+          None => return Ok(0),
+        };
+
+        let until = self.env().block_timestamp().min(schedule.end);
+        // Only whole seconds are emitted; the rest carries over to the next call.
+        let elapsed_secs = until.saturating_sub(schedule.last_emitted) / 1000;
+        let mut value = schedule.per_sec.checked_mul(elapsed_secs as Balance).ok_or(Error::Overflow)?;
+        if let Some(cap) = self.current_cap() {
+          value = value.min(cap.saturating_sub(self.total_supply));
+        }
+
+        schedule.last_emitted += elapsed_secs * 1000;
+        self.emission = Some(schedule.clone());
+        if value > 0 {
+          self.mint_to(&schedule.recipient, value)?;
+        }
+
+        Ok(value)
+      }
+
+      #[ink(message)]
+      pub fn paused(&self) -> bool {
+        self.pause_level() != PauseLevel::None
+      }
+
+      #[ink(message)]
+      pub fn pause_level(&self) -> PauseLevel {
+        if self.breaker_pause_expired() {
+          return PauseLevel::None;
+        }
+        self.pause_level
+      }
+
+      #[ink(message)]
+      pub fn set_pause_level(&mut self, level: PauseLevel) -> Result<()> {
+        self.ensure_owner()?;
+        self.pause_level = level;
+        self.breaker_tripped = false;
+        Ok(())
+      }
+
+      /// Pauses transfers, as `PauseLevel::TransfersOnly`, once more than `threshold` moves within `window_blocks` blocks.
+      /// Only net outflow counts, so tokens sent back and forth add nothing, and the pause
+      /// lifts itself once the window that tripped it has passed.
+      #[ink(message)]
+      pub fn set_circuit_breaker(&mut self, threshold: Option<Balance>, window_blocks: BlockNumber) -> Result<()> {
+        self.ensure_owner()?;
+        self.breaker_threshold = threshold;
+        self.breaker_window_blocks = window_blocks;
+        Ok(())
+      }
+
+      /// Clears the tripped breaker and its outflow counter, resuming transfers. A pause
+      /// set through `set_pause_level` stays in place.
+      #[ink(message)]
+      pub fn reset_circuit_breaker(&mut self) -> Result<()> {
+        self.ensure_owner()?;
+        if self.breaker_tripped {
+          self.pause_level = PauseLevel::None;
+          self.breaker_tripped = false;
+        }
+        self.breaker_window_start = self.env().block_number();
+        self.breaker_outflow = 0;
+        Ok(())
+      }
+
+      /// Number of transfers `account` has sent and received, in that order.
+      #[ink(message)]
+      pub fn transfer_stats(&self, account: AccountId) -> (u64, u64) {
+        (
+          self.sent_count.get(account).unwrap_or_default(),
+          self.received_count.get(account).unwrap_or_default(),
+        )
+      }
+
+      /// Escrows `value` for `to`, who must `claim` it before the `expiry` timestamp.
+      /// Once expired, only the sender can take it back with `reclaim_expired`.
+      #[ink(message)]
+      pub fn transfer_claimable(&mut self, to: AccountId, value: Balance, expiry: Timestamp) -> Result<u64> {
+        if expiry <= self.env().block_timestamp() {
+          return Err(Error::ClaimExpired);
+        }
+        let contract = self.env().account_id();
+        if to == contract {
+          return Err(Error::NotAllowed);
+        }
+        self.ensure_registered(&to)?;
+
+        let sender = self.env().caller();
+        self.transfer_helper(&sender, &contract, value)?;
+        self.escrowed += value;
+
+        let id = self.next_claimable_transfer_id;
+        self.claimable_transfers.insert(id, &ClaimableTransfer { from: sender, to, value, expiry });
+        self.next_claimable_transfer_id += 1;
+
+        Ok(id)
+      }
+
+      #[ink(message)]
+      pub fn claim(&mut self, id: u64) -> Result<()> {
+        let claimable = self.claimable_transfers.get(id).ok_or(Error::ClaimableTransferNotFound)?;
+        if self.env().caller() != claimable.to {
+          return Err(Error::NotAllowed);
+        }
+        if self.env().block_timestamp() >= claimable.expiry {
+          return Err(Error::ClaimExpired);
+        }
+
+        self.claimable_transfers.remove(id);
+        let contract = self.env().account_id();
+        self.escrowed -= claimable.value;
+        self.transfer_helper(&contract, &claimable.to, claimable.value)
+      }
+
+      #[ink(message)]
+      pub fn reclaim_expired(&mut self, id: u64) -> Result<()> {
+        let claimable = self.claimable_transfers.get(id).ok_or(Error::ClaimableTransferNotFound)?;
+        if self.env().caller() != claimable.from {
+          return Err(Error::NotAllowed);
+        }
+        if self.env().block_timestamp() < claimable.expiry {
+          return Err(Error::NotExpired);
+        }
+
+        self.claimable_transfers.remove(id);
+        let contract = self.env().account_id();
+        self.escrowed -= claimable.value;
+        self.transfer_helper(&contract, &claimable.from, claimable.value)
+      }
+
+      #[ink(message)]
+      pub fn claimable_transfer(&self, id: u64) -> Option<ClaimableTransfer> {
+        self.claimable_transfers.get(id)
+      }
+
+      /// Tokens the contract holds on behalf of holders: open escrows plus forced savings.
+      /// Revenue awaiting distribution is not counted.
+      #[ink(message)]
+      pub fn total_value_locked(&self) -> Balance {
+        self.escrowed + self.savings_total
+      }
+
+      /// `total_supply` minus tokens sent to the zero account, which nobody can spend.
+      #[ink(message)]
+      pub fn effective_supply(&self) -> Balance {
+        self.total_supply - self.balance_of(AccountId::from([0x0; 32]))
+      }
+
+      /// What `total_supply` would be at `at_timestamp` if every emission up to then
+      /// were claimed and nothing were minted or burned otherwise, limited by the supply
+      /// cap as of `at_timestamp`.
+      #[ink(message)]
+      pub fn projected_supply(&self, at_timestamp: Timestamp) -> Balance {
+        let projected = match &self.emission {
+          Some(schedule) => {
+            let until = at_timestamp.min(schedule.end);
+            let elapsed_secs = until.saturating_sub(schedule.last_emitted) / 1000;
+            let pending = schedule.per_sec.saturating_mul(elapsed_secs as Balance);
+            self.total_supply.saturating_add(pending)
+          }
+          None => self.total_supply,
+        };
+        match self.cap_at(at_timestamp) {
+          Some(cap) => projected.min(cap.max(self.total_supply)),
+          None => projected,
+        }
+      }
+
+      /// Tokens the emission schedule can still mint, claimed or not, before its end,
+      /// limited to the room left under `current_cap` when a supply cap is set. Without a
+      /// schedule this is the room under the cap, or 0 when supply is uncapped.
+      #[ink(message)]
+      pub fn remaining_mintable(&self) -> Balance {
+        let room = self.current_cap().map(|cap| cap.saturating_sub(self.total_supply));
+        match &self.emission {
+          Some(schedule) => {
+            let remaining_secs = schedule.end.saturating_sub(schedule.last_emitted) / 1000;
+            let scheduled = schedule.per_sec.saturating_mul(remaining_secs as Balance);
+            room.map_or(scheduled, |room| scheduled.min(room))
+          }
+          None => room.unwrap_or(0),
+        }
+      }
+
+      /// The block timestamp, in milliseconds, that every time-based check compares against.
+      #[ink(message)]
+      pub fn now(&self) -> Timestamp {
+        self.env().block_timestamp()
+      }
+
+      #[ink(message)]
+      pub fn delayed_transfer(&self, id: u64) -> Option<DelayedTransfer> {
+        self.delayed_transfers.get(id)
+      }
+
+      /// Pays `recipients` in order until the next amount would exceed `budget`,
+      /// skipping everyone after that point. Returns the number of recipients paid.
+      #[ink(message)]
+      pub fn batch_transfer_budgeted(&mut self, recipients: Vec<AccountId>, amounts: Vec<Balance>, budget: Balance) -> Result<u32> {
+        if recipients.len() != amounts.len() {
+          return Err(Error::LengthMismatch);
+        }
+
+        let sender = self.env().caller();
+        let mut remaining = budget;
+        let mut paid = 0;
+        for (to, value) in recipients.iter().zip(amounts) {
+          if value > remaining {
+            break;
+          }
+          if self.memo_required_above.is_some_and(|threshold| value > threshold) {
+            return Err(Error::MemoRequired);
+          }
+          self.transfer_locking_savings(&sender, to, value)?;
+          remaining -= value;
+          paid += 1;
+        }
+
+        Ok(paid)
+      }
+
+      #[ink(message)]
+      pub fn sig_nonce(&self, owner: AccountId) -> u64 {
+        self.sig_nonces.get(owner).unwrap_or_default()
+      }
+
+      /// The hash `owner` signs to authorize `ops` through `execute_batch_by_sig`. It
+      /// binds the batch to this contract, the nonce and the deadline.
+      #[ink(message)]
+      pub fn batch_signing_hash(&self, owner: AccountId, ops: Vec<Op>, nonce: u64, deadline: Timestamp) -> [u8; 32] {
+        self.env().hash_encoded::<Blake2x256, _>(&(self.env().account_id(), owner, ops, nonce, deadline))
+      }
+
+      /// Runs `ops` as `owner` on the strength of one ECDSA signature over
+      /// `batch_signing_hash`, so a relayer can submit them. `owner` must be the
+      /// Blake2 hash of the signing key, as for Substrate ECDSA accounts. Any failing op
+      /// reverts the whole batch.
+      #[ink(message)]
+      pub fn execute_batch_by_sig(&mut self, owner: AccountId, ops: Vec<Op>, nonce: u64, deadline: Timestamp, signature: [u8; 65]) -> Result<()> {
+        if ops.len() > MAX_SIGNED_OPS {
+          return Err(Error::BatchTooLarge);
+        }
+        if self.env().block_timestamp() > deadline {
+          return Err(Error::SignatureExpired);
+        }
+        if nonce != self.sig_nonce(owner) {
+          return Err(Error::InvalidNonce);
+        }
+
+        let hash = self.batch_signing_hash(owner, ops.clone(), nonce, deadline);
+        let public_key = self.env().ecdsa_recover(&signature, &hash).map_err(|_| Error::InvalidSignature)?;
+        if AccountId::from(self.env().hash_bytes::<Blake2x256>(&public_key)) != owner {
+          return Err(Error::InvalidSignature);
+        }
+        self.sig_nonces.insert(owner, &(nonce + 1));
+
+        for op in ops {
+          match op {
+            Op::Transfer { to, value } => {
+              if self.memo_required_above.is_some_and(|threshold| value > threshold) {
+                return Err(Error::MemoRequired);
+              }
+              self.transfer_locking_savings(&owner, &to, value)?;
+            }
+            Op::Approve { spender, value } => self.approve_helper(&owner, &spender, value)?,
+            Op::Burn { value } => self.burn_from(&owner, value)?,
+          }
+        }
+
+        Ok(())
+      }
+
+      /// Sweeps the full balance of every account in `froms` into `to`. Each account must
+      /// have approved the owner for at least its balance beforehand; the sweep spends
+      /// that allowance like `transfer_from`.
+      #[ink(message)]
+      pub fn consolidate(&mut self, froms: Vec<AccountId>, to: AccountId) -> Result<()> {
+        self.ensure_owner()?;
+        if froms.len() > MAX_CONSOLIDATE {
+          return Err(Error::BatchTooLarge);
+        }
+
+        let owner = self.env().caller();
+        for from in froms {
+          let balance = self.balance_of(from);
+          self.spend_allowance(&from, &owner, balance)?;
+          self.transfer_helper(&from, &to, balance)?;
+        }
+
+        Ok(())
+      }
+
+      /// Transfers like `transfer` and records `memo` as a payment reference.
+      #[ink(message)]
+      pub fn transfer_with_memo(&mut self, to: AccountId, value: Balance, memo: Vec<u8>) -> Result<()> {
+        let sender = self.env().caller();
+        self.transfer_locking_savings(&sender, &to, value)?;
+
+        self.env().emit_event(TransferMemo {
+          from: sender,
+          to,
+          value,
+          memo,
+        });
+
+        Ok(())
+      }
+
+      /// Transfers like `transfer` and forwards the attached native value to `to` as a
+      /// tip. Either leg failing reverts the whole call.
+      #[ink(message, payable)]
+      pub fn transfer_with_tip(&mut self, to: AccountId, value: Balance) -> Result<()> {
+        self.transfer(to, value)?;
+
+        let tip = self.env().transferred_value();
+        if tip > 0 {
+          self.env().transfer(to, tip).map_err(|_| Error::TipTransferFailed)?;
+        }
+
+        Ok(())
+      }
+
+      #[ink(message)]
+      pub fn memo_required_above(&self) -> Option<Balance> {
+        self.memo_required_above
+      }
+
+      #[ink(message)]
+      pub fn set_memo_required_above(&mut self, threshold: Option<Balance>) -> Result<()> {
+        self.ensure_owner()?;
+        self.memo_required_above = threshold;
+        Ok(())
+      }
+
+      /// Moves the whole balance of `old`, its savings and every allowance it granted
+      /// over to `new`. Callable by the owner or by `old` itself. An allowance `new`
+      /// already granted to one of the same spenders is replaced. The balance moves
+      /// directly, so pauses, the gate and transfer limits do not block an owner-run
+      /// recovery. When `old` migrates itself, soulbound mode, recipient registration and
+      /// veto locks still apply. Sent transfers count against `new` afterwards.
+      #[ink(message)]
+      pub fn migrate_balance(&mut self, old: AccountId, new: AccountId) -> Result<()> {
+        let caller = self.env().caller();
+        if caller != old && self.ensure_owner().is_err() {
+          return Err(Error::NotAllowed);
+        }
+        let contract = self.env().account_id();
+        if old == new || old == contract || new == contract {
+          return Err(Error::NotAllowed);
+        }
+        if caller == old {
+          if !self.transferable {
+            return Err(Error::NonTransferable);
+          }
+          self.ensure_registered(&new)?;
+          if self.veto_locked(old) > 0 {
+            return Err(Error::BalanceLocked);
+          }
+        }
+
+        let balance = self.balance_of(old);
+        let balance_new = self.balance_of(new);
+        self.set_balance(&old, 0);
+        self.set_balance(&new, balance_new + balance);
+        self.env().emit_event(Transfer {
+          from: Some(old),
+          to: Some(new),
+          value: balance,
+        });
+
+        if let Some(sent) = self.sent_count.take(old) {
+          let existing = self.sent_count.get(new).unwrap_or_default();
+          self.sent_count.insert(new, &(existing + sent));
+        }
+
+        if let Some((locked, until)) = self.veto_locks.take(old) {
+          let (existing, existing_until) = self.veto_locks.get(new).unwrap_or_default();
+          self.veto_locks.insert(new, &(locked.saturating_add(existing), until.max(existing_until)));
+        }
+
+        if let Some(moved) = self.savings.take(old) {
+          let savings = match self.savings.get(new) {
+            Some(existing) => Savings {
+              amount: existing.amount + moved.amount,
+              unlock_at: existing.unlock_at.max(moved.unlock_at),
+              newer_amount: existing.newer_amount + moved.newer_amount,
+              newer_unlock_at: existing.newer_unlock_at.max(moved.newer_unlock_at),
+            },
+            None => moved,
+          };
+          self.savings.insert(new, &savings);
+        }
+
+        for spender in self.spenders.take(old).unwrap_or_default() {
+          if let Some(expiry) = self.allowance_expiry.take((old, spender)) {
+            self.allowance_expiry.insert((new, spender), &expiry);
+          } else {
+            self.allowance_expiry.remove((new, spender));
+          }
+
+          if let Some(recurring) = self.recurring_allowances.take((old, spender)) {
+            self.clear_allowance(&new, &spender);
+            self.percent_allowances.remove((new, spender));
+            self.track_spender(&new, &spender);
+            self.env().emit_event(Approve {
+              from: new,
+              to: spender,
+              value: recurring.per_period,
+            });
+            self.recurring_allowances.insert((new, spender), &recurring);
+            self.touch_allowance(&new, &spender);
+            continue;
+          }
+
+          if let Some(bps) = self.percent_allowances.take((old, spender)) {
+            self.recurring_allowances.remove((new, spender));
+            self.clear_allowance(&new, &spender);
+            self.track_spender(&new, &spender);
+            self.percent_allowances.insert((new, spender), &bps);
+            self.touch_allowance(&new, &spender);
+            self.env().emit_event(PercentApproval {
+              from: new,
+              to: spender,
+              bps,
+            });
+            continue;
+          }
+
+          let value = self.clear_allowance(&old, &spender);
+          self.percent_allowances.remove((new, spender));
+          self.recurring_allowances.remove((new, spender));
+          self.set_allowance(&new, &spender, value);
+          self.env().emit_event(Approve {
+            from: new,
+            to: spender,
+            value,
+          });
+        }
+
+        Ok(())
+      }
+
+      /// Splits `amount` of the caller's tokens across every other holder in proportion
+      /// to their balances. The rounding remainder goes to `distribution_sink`, or to the
+      /// largest recipient when no sink is set, so exactly `amount` is paid out.
+      ///
+      /// This walks the whole holder set and is only suitable for small holder counts.
+      #[ink(message)]
+      pub fn distribute_proportional(&mut self, amount: Balance) -> Result<()> {
+        let sender = self.env().caller();
+        self.distribute_from(&sender, amount)
+      }
+
+      /// Adds `value` of the caller's tokens to the revenue paid out by the next
+      /// `trigger_distribution`.
+      #[ink(message)]
+      pub fn deposit_revenue(&mut self, value: Balance) -> Result<()> {
+        let sender = self.env().caller();
+        let contract = self.env().account_id();
+        self.transfer_helper(&sender, &contract, value)?;
+        self.pending_revenue += value;
+        Ok(())
+      }
+
+      #[ink(message)]
+      pub fn pending_revenue(&self) -> Balance {
+        self.pending_revenue
+      }
+
+      /// Pays all pending revenue out to holders, like `distribute_proportional`, once
+      /// `distribution_interval_secs` has passed since the last payout. Anyone may call it.
+      #[ink(message)]
+      pub fn trigger_distribution(&mut self) -> Result<Balance> {
+        let now = self.env().block_timestamp();
+        let interval = self.distribution_interval_secs.saturating_mul(1000);
+        if now < self.last_distribution_at.saturating_add(interval) {
+          return Err(Error::DistributionTooSoon);
+        }
+
+        let amount = self.pending_revenue;
+        if amount > 0 {
+          let contract = self.env().account_id();
+          self.distribute_from(&contract, amount)?;
+        }
+        self.pending_revenue = 0;
+        self.last_distribution_at = now;
+
+        Ok(amount)
+      }
+
+      /// Sets the minimum time between two `trigger_distribution` payouts, counted from now.
+      #[ink(message)]
+      pub fn set_distribution_interval(&mut self, interval_secs: u64) -> Result<()> {
+        self.ensure_owner()?;
+        self.distribution_interval_secs = interval_secs;
+        self.last_distribution_at = self.env().block_timestamp();
+        Ok(())
+      }
+
+      fn distribute_from(&mut self, sender: &AccountId, amount: Balance) -> Result<()> {
+        let contract = self.env().account_id();
+        let mut recipients = self.holders_except(sender);
+        // Tokens escrowed by the contract itself are not entitled to a share.
+        recipients.retain(|(holder, _)| *holder != contract);
+        let weight: Balance = recipients.iter().map(|(_, balance)| balance).sum();
+        if weight == 0 {
+          return Err(Error::NoRecipients);
+        }
+
+        let mut largest = recipients[0];
+        let mut distributed = 0;
+        for (holder, balance) in recipients.iter() {
+          let share = amount.checked_mul(*balance).ok_or(Error::Overflow)? / weight;
+          self.transfer_helper(sender, holder, share)?;
+          distributed += share;
+          if *balance > largest.1 {
+            largest = (*holder, *balance);
+          }
+        }
+
+        let remainder = amount - distributed;
+        if remainder > 0 {
+          let sink = self.distribution_sink.unwrap_or(largest.0);
+          self.transfer_helper(sender, &sink, remainder)?;
+        }
+
+        Ok(())
+      }
+
+      #[ink(message)]
+      pub fn distribution_sink(&self) -> Option<AccountId> {
+        self.distribution_sink
+      }
+
+      #[ink(message)]
+      pub fn set_distribution_sink(&mut self, sink: Option<AccountId>) -> Result<()> {
+        self.ensure_owner()?;
+        self.distribution_sink = sink;
+        Ok(())
+      }
+
+      /// Lets `spender` pull up to `bps` / 10000 of the caller's balance at the time of
+      /// each `transfer_from`. The allowance follows the balance and is not decremented
+      /// when spent; it replaces any fixed allowance for the same spender. Rejected when
+      /// the share of the current balance exceeds `large_approval_threshold`, and never
+      /// worth more than that threshold as the balance grows.
+      #[ink(message)]
+      pub fn approve_percent(&mut self, spender: AccountId, bps: u16) -> Result<()> {
+        if bps > 10000 {
+          return Err(Error::InvalidBasisPoints);
+        }
+
+        let sender = self.env().caller();
+        let share = self.balance_of(sender).saturating_mul(bps as Balance) / 10000;
+        if self.large_approval_threshold.is_some_and(|threshold| share > threshold) {
+          return Err(Error::ApprovalTooLarge);
+        }
+        self.clear_allowance(&sender, &spender);
+        self.recurring_allowances.remove((sender, spender));
+        self.track_spender(&sender, &spender);
+        self.percent_allowances.insert((sender, spender), &bps);
+        self.touch_allowance(&sender, &spender);
+        self.stamp_allowance_expiry(&sender, &spender);
+
+        self.env().emit_event(PercentApproval {
+          from: sender,
+          to: spender,
+          bps,
+        });
+
+        Ok(())
+      }
+
+      /// Lets `spender` pull up to `per_period` in each `period_secs` window starting now;
+      /// unspent allowance does not carry over. Replaces any other allowance for `spender`.
+      /// Rejected when `per_period` exceeds `large_approval_threshold`.
+      #[ink(message)]
+      pub fn approve_recurring(&mut self, spender: AccountId, per_period: Balance, period_secs: u64) -> Result<()> {
+        if period_secs == 0 {
+          return Err(Error::InvalidPeriod);
+        }
+        if self.large_approval_threshold.is_some_and(|threshold| per_period > threshold) {
+          return Err(Error::ApprovalTooLarge);
+        }
+        if let Some(ceiling) = self.max_allowance_per_spender {
+          if per_period > ceiling {
+            return Err(Error::AllowanceCeilingExceeded);
+          }
+        }
+
+        let sender = self.env().caller();
+        self.clear_allowance(&sender, &spender);
+        self.percent_allowances.remove((sender, spender));
+        self.track_spender(&sender, &spender);
+        self.recurring_allowances.insert((sender, spender), &RecurringAllowance {
+          per_period,
+          period_secs,
+          anchor: self.env().block_timestamp(),
+          period: 0,
+          spent: 0,
+        });
+        self.touch_allowance(&sender, &spender);
+        self.stamp_allowance_expiry(&sender, &spender);
+
+        self.env().emit_event(Approve {
+          from: sender,
+          to: spender,
+          value: per_period,
+        });
+
+        Ok(())
+      }
+
+      #[ink(message)]
+      pub fn recurring_allowance(&self, owner: AccountId, spender: AccountId) -> Option<RecurringAllowance> {
+        self.recurring_allowances.get((owner, spender))
+      }
+
+      /// Gives every approval made from now on a lifetime of `ttl_secs`; `None` makes
+      /// new approvals permanent again. Existing approvals keep their expiry.
+      #[ink(message)]
+      pub fn set_default_allowance_ttl(&mut self, ttl_secs: Option<u64>) -> Result<()> {
+        self.ensure_owner()?;
+        self.default_allowance_ttl = ttl_secs;
+        Ok(())
+      }
+
+      /// Timestamp of the last change to the `owner`/`spender` allowance, including
+      /// decrements made by `transfer_from`; `None` if it was never set.
+      #[ink(message)]
+      pub fn allowance_updated_at(&self, owner: AccountId, spender: AccountId) -> Option<Timestamp> {
+        self.allowance_updated_at.get((owner, spender))
+      }
+
+      #[ink(message)]
+      pub fn allowance_expiry(&self, owner: AccountId, spender: AccountId) -> Option<Timestamp> {
+        self.allowance_expiry.get((owner, spender))
+      }
+
+      #[ink(message)]
+      pub fn set_large_approval_threshold(&mut self, threshold: Option<Balance>) -> Result<()> {
+        self.ensure_owner()?;
+        self.large_approval_threshold = threshold;
+        Ok(())
+      }
+
+      /// Limits every account other than the contract to `max` sends over its lifetime.
+      #[ink(message)]
+      pub fn set_max_transfers_per_account(&mut self, max: Option<u32>) -> Result<()> {
+        self.ensure_owner()?;
+        self.max_transfers_per_account = max;
+        Ok(())
+      }
+
+      #[ink(message)]
+      pub fn pending_large_approval(&self, owner: AccountId, spender: AccountId) -> Option<Balance> {
+        self.pending_large_approvals.get((owner, spender))
+      }
+
+      /// Applies the approval for `spender` the caller staged above `large_approval_threshold`.
+      #[ink(message)]
+      pub fn confirm_large_approval(&mut self, spender: AccountId) -> Result<()> {
+        let sender = self.env().caller();
+        let value = self.pending_large_approvals.take((sender, spender)).ok_or(Error::NoPendingApproval)?;
+        self.apply_approval(&sender, &spender, value)
+      }
+
+      /// Sets the caller's allowance for each spender to exactly the matching target,
+      /// as if `approve` were called once per entry.
+      #[ink(message)]
+      pub fn set_allowances(&mut self, spenders: Vec<AccountId>, targets: Vec<Balance>) -> Result<()> {
+        if spenders.len() != targets.len() {
+          return Err(Error::LengthMismatch);
+        }
+
+        let sender = self.env().caller();
+        for (spender, target) in spenders.iter().zip(targets) {
+          self.approve_helper(&sender, spender, target)?;
+        }
+
+        Ok(())
+      }
+
+      /// Spenders `owner` currently has a non-zero allowance entry for.
+      #[ink(message)]
+      pub fn spenders_of(&self, owner: AccountId) -> Vec<AccountId> {
+        self.spenders.get(owner).unwrap_or_default()
+      }
+
+      /// Allowance for each `(owner, spender)` pair, in input order.
+      #[ink(message)]
+      pub fn allowances_batch(&self, pairs: Vec<(AccountId, AccountId)>) -> Result<Vec<Balance>> {
+        if pairs.len() > MAX_BATCH_QUERY {
+          return Err(Error::BatchTooLarge);
+        }
+        Ok(pairs.into_iter().map(|(owner, spender)| self.allowance(owner, spender)).collect())
+      }
+
+      /// Combined fixed allowance every owner has granted `spender`, saturating at
+      /// `Balance::MAX`. Percentage allowances move with balances and are not included;
+      /// expired allowances are, until they are overwritten or spent.
+      #[ink(message)]
+      pub fn total_allowance_to(&self, spender: AccountId) -> Balance {
+        self.total_allowance_to.get(spender).unwrap_or_default()
+      }
+
+      /// Whether a transfer to `to` would add a new holder.
+      #[ink(message)]
+      pub fn transfer_creates_holder(&self, to: AccountId) -> bool {
+        self.balance_of(to) == 0
+      }
+
+      /// Whether `account` has held a nonzero balance at any point, even if it no longer does.
+      #[ink(message)]
+      pub fn has_ever_held(&self, account: AccountId) -> bool {
+        self.ever_held.get(account).unwrap_or_default()
+      }
+
+      /// The `n` largest holders, largest first. `n` is capped at `MAX_TOP_HOLDERS`. The
+      /// contract's own account is never ranked.
+      #[ink(message)]
+      pub fn top_holders(&self, n: u32) -> Vec<(AccountId, Balance)> {
+        let n = (n as usize).min(MAX_TOP_HOLDERS);
+        let mut top = self.top_holders.get_or_default();
+        top.truncate(n);
+        top
+      }
+
+      /// 1-based position of `account` by balance, with equal balances sharing a rank.
+      /// The contract's own account is not ranked. Answered from `top_holders` when the
+      /// account is in it; otherwise every holder is read, which is O(holders).
+      #[ink(message)]
+      pub fn rank_of(&self, account: AccountId) -> Option<u32> {
+        let contract = self.env().account_id();
+        let balance = self.balance_of(account);
+        if balance == 0 || account == contract {
+          return None;
+        }
+
+        let mut listed = self.top_holders.get_or_default();
+        listed.truncate(MAX_TOP_HOLDERS);
+        let ahead = if listed.iter().any(|(holder, _)| *holder == account) {
+          listed.iter().filter(|(_, held)| *held > balance).count()
+        } else {
+          self.holders_except(&account).iter()
+            .filter(|(holder, held)| *holder != contract && *held > balance)
+            .count()
+        };
+        Some(ahead as u32 + 1)
+      }
+
+      #[ink(message)]
+      pub fn holder_count(&self) -> u32 {
+        self.holder_count
+      }
+
+      /// Snapshot of every holder but `excluded`, with their current balances.
+      fn holders_except(&self, excluded: &AccountId) -> Vec<(AccountId, Balance)> {
+        (0..self.holder_count)
+          .filter_map(|index| self.holders.get(index))
+          .filter(|holder| holder != excluded)
+          .map(|holder| (holder, self.balance_of(holder)))
+          .collect()
+      }
+
+      /// Adds `value` to the current breaker window and trips the breaker when the
+      /// threshold is crossed. The crossing transfer itself still completes, since
+      /// failing it would also roll back the pause.
+      fn record_outflow(&mut self, from: &AccountId, to: &AccountId, value: Balance) {
+        let threshold = match self.breaker_threshold {
+          Some(threshold) => threshold,
+          None => return,
+        };
+
+        let block = self.env().block_number();
+        if block >= self.breaker_window_start.saturating_add(self.breaker_window_blocks) {
+          self.breaker_window_start = block;
+          self.breaker_outflow = 0;
+        }
+
+        let before = self.net_outflow(from).saturating_add(self.net_outflow(to));
+        let mut flow = self.window_flow(from);
+        flow.sent = flow.sent.saturating_add(value);
+        self.breaker_flows.insert(from, &flow);
+        let mut flow = self.window_flow(to);
+        flow.received = flow.received.saturating_add(value);
+        self.breaker_flows.insert(to, &flow);
+        let after = self.net_outflow(from).saturating_add(self.net_outflow(to));
+        self.breaker_outflow = self.breaker_outflow.saturating_add(after).saturating_sub(before);
+        if self.breaker_outflow > threshold {
+          if self.pause_level == PauseLevel::None {
+            self.pause_level = PauseLevel::TransfersOnly;
+            self.breaker_tripped = true;
+          }
+          self.env().emit_event(CircuitBreakerTripped {
+            outflow: self.breaker_outflow,
+            block,
+          });
+        }
+      }
+
+      /// `account`'s breaker flow in the current window.
+      fn window_flow(&self, account: &AccountId) -> BreakerFlow {
+        match self.breaker_flows.get(account) {
+          Some(flow) if flow.window_start == self.breaker_window_start => flow,
+          _ => BreakerFlow { window_start: self.breaker_window_start, ..Default::default() },
+        }
+      }
+
+      fn net_outflow(&self, account: &AccountId) -> Balance {
+        let flow = self.window_flow(account);
+        flow.sent.saturating_sub(flow.received)
+      }
+
+      /// Whether the breaker's pause belongs to a window that has since ended.
+      fn breaker_pause_expired(&self) -> bool {
+        self.breaker_tripped
+          && self.env().block_number() >= self.breaker_window_start.saturating_add(self.breaker_window_blocks)
+      }
+
+      /// Enforces `min_mint_interval_secs` between owner mints and records this one.
+      /// Scheduled emission is not throttled.
+      fn throttle_mint(&mut self) -> Result<()> {
+        self.ensure_mint_interval()?;
+        self.last_mint_at = Some(self.env().block_timestamp());
+        Ok(())
+      }
+
+      fn ensure_mint_interval(&self) -> Result<()> {
+        let now = self.env().block_timestamp();
+        if let (Some(interval), Some(last)) = (self.min_mint_interval_secs, self.last_mint_at) {
+          if now < last.saturating_add(interval.saturating_mul(1000)) {
+            return Err(Error::MintTooSoon);
+          }
+        }
+        Ok(())
+      }
+
+      fn burn_from(&mut self, from: &AccountId, value: Balance) -> Result<()> {
+        if self.pause_level == PauseLevel::Full {
+          return Err(Error::Paused);
+        }
+        let balance = self.balance_of(*from);
+        if value > balance {
+          return Err(Error::BalanceTooLow);
+        }
+        if balance - value < self.veto_locked(*from) {
+          return Err(Error::BalanceLocked);
+        }
+
+        self.set_balance(from, balance - value);
+        self.total_supply -= value;
+
+        self.env().emit_event(Transfer {
+          from: Some(*from),
+          to: None,
+          value,
+        });
+
+        Ok(())
+      }
+
+      fn mint_to(&mut self, to: &AccountId, value: Balance) -> Result<()> {
+        let total_supply = self.total_supply.checked_add(value).ok_or(Error::Overflow)?;
+        if self.current_cap().is_some_and(|cap| total_supply > cap) {
+          return Err(Error::CapExceeded);
+        }
+        self.total_supply = total_supply;
+        let balance = self.balance_of(*to);
+        self.set_balance(to, balance + value);
+
+        self.env().emit_event(Transfer {
+          from: None,
+          to: Some(*to),
+          value,
+        });
+
+        Ok(())
+      }
+
+      /// Writes a balance and keeps the holder set in step with it.
+      fn set_balance(&mut self, who: &AccountId, value: Balance) {
+        self.balances.insert(who, &value);
+
+        let index = self.holder_index.get(who);
+        if value > 0 && index.is_none() {
+          self.holders.insert(self.holder_count, who);
+          self.holder_index.insert(who, &self.holder_count);
+          self.holder_count += 1;
+          self.ever_held.insert(who, &true);
+        } else if value == 0 {
+          if let Some(index) = index {
+            let last = self.holder_count - 1;
+            if index != last {
+              let moved = self.holders.get(last).expect("holder set is contiguous");
+              self.holders.insert(index, &moved);
+              self.holder_index.insert(moved, &index);
+            }
+            self.holders.remove(last);
+            self.holder_index.remove(who);
+            self.holder_count = last;
+          }
+        }
+
+        self.update_top_holders(who, value);
+      }
+
+      /// Keeps `top_holders` sorted after `who`'s balance changed to `value`.
+      ///
+      /// Only `who` is re-ranked. A listed holder whose balance drops slides down into
+      /// the `TOP_HOLDER_BUFFER` entries below the visible list instead of triggering a
+      /// scan, so the buffer's tail can lag behind unlisted holders until their own
+      /// balances next change.
+      fn update_top_holders(&mut self, who: &AccountId, value: Balance) {
+        let mut top = self.top_holders.get_or_default();
+        if let Some(index) = top.iter().position(|(holder, _)| holder == who) {
+          top.remove(index);
+        }
+        if value > 0 && *who != self.env().account_id() {
+          let index = top.iter().position(|(_, listed)| *listed < value).unwrap_or(top.len());
+          top.insert(index, (*who, value));
+          top.truncate(MAX_TOP_HOLDERS + TOP_HOLDER_BUFFER);
+        }
+        self.top_holders.set(&top);
+      }
+
+      fn stamp_allowance_expiry(&mut self, owner: &AccountId, spender: &AccountId) {
+        match self.default_allowance_ttl {
+          Some(ttl) => {
+            let expiry = self.env().block_timestamp().saturating_add(ttl.saturating_mul(1000));
+            self.allowance_expiry.insert((*owner, *spender), &expiry);
+          }
+          None => self.allowance_expiry.remove((*owner, *spender)),
+        }
+      }
+
+      fn ensure_registered(&self, account: &AccountId) -> Result<()> {
+        if self.require_registration && !self.registered.contains(account) {
+          return Err(Error::RecipientNotRegistered);
+        }
+        Ok(())
+      }
+
+      fn touch_allowance(&mut self, owner: &AccountId, spender: &AccountId) {
+        self.allowance_updated_at.insert((*owner, *spender), &self.env().block_timestamp());
+      }
+
+      fn track_spender(&mut self, owner: &AccountId, spender: &AccountId) {
+        let mut spenders = self.spenders.get(owner).unwrap_or_default();
+        if !spenders.contains(spender) {
+          spenders.push(*spender);
+          self.spenders.insert(owner, &spenders);
+        }
+      }
+
+      /// Drops `spender` from `owner`'s spender list.
+      fn untrack_spender(&mut self, owner: &AccountId, spender: &AccountId) {
+        let mut spenders = self.spenders.get(owner).unwrap_or_default();
+        spenders.retain(|tracked| tracked != spender);
+        if spenders.is_empty() {
+          self.spenders.remove(owner);
+        } else {
+          self.spenders.insert(owner, &spenders);
+        }
+      }
+
+      /// Writes a fixed allowance. A zero allowance is removed from storage and from the
+      /// owner's spender list rather than stored.
+      fn set_allowance(&mut self, owner: &AccountId, spender: &AccountId, value: Balance) {
+        if value == 0 {
+          self.clear_allowance(owner, spender);
+          self.untrack_spender(owner, spender);
+          self.allowance_expiry.remove((*owner, *spender));
+          return;
+        }
+
+        self.track_spender(owner, spender);
+        let previous = self.allowances.get((*owner, *spender)).unwrap_or_default();
+        self.allowances.insert(&(*owner, *spender), &value);
+        self.touch_allowance(owner, spender);
+        let total = self.total_allowance_to(*spender).saturating_sub(previous).saturating_add(value);
+        self.total_allowance_to.insert(spender, &total);
+      }
+
+      /// Drops an allowance entry without touching the owner's spender list.
+      fn clear_allowance(&mut self, owner: &AccountId, spender: &AccountId) -> Balance {
+        let value = self.allowances.take((*owner, *spender)).unwrap_or_default();
+        self.touch_allowance(owner, spender);
+        let total = self.total_allowance_to(*spender).saturating_sub(value);
+        self.total_allowance_to.insert(spender, &total);
+        value
+      }
+
+      /// Index of the current `period_secs` window since `recurring.anchor`.
+      fn recurring_period(&self, recurring: &RecurringAllowance) -> u64 {
+        let elapsed = self.env().block_timestamp().saturating_sub(recurring.anchor);
+        elapsed / recurring.period_secs.saturating_mul(1000)
+      }
+
+      fn ensure_owner(&self) -> Result<()> {
+        if self.owner == AccountId::from([0x0; 32]) || self.env().caller() != self.owner {
+          return Err(Error::NotOwner);
+        }
+        Ok(())
+      }
+
+      /// Approves `value` right away, or stages it for `confirm_large_approval` when it
+      /// exceeds `large_approval_threshold`. The current allowance stays until then.
+      pub fn approve_helper(&mut self, owner: &AccountId, spender: &AccountId, value: Balance) -> Result<()> {
+        if self.large_approval_threshold.is_some_and(|threshold| value > threshold) {
+          self.pending_large_approvals.insert((*owner, *spender), &value);
+          return Ok(());
+        }
+
+        self.pending_large_approvals.remove((*owner, *spender));
+        self.apply_approval(owner, spender, value)
+      }
+
+      fn apply_approval(&mut self, owner: &AccountId, spender: &AccountId, value: Balance) -> Result<()> {
+        if let Some(ceiling) = self.max_allowance_per_spender {
+          if value > ceiling {
+            return Err(Error::AllowanceCeilingExceeded);
+          }
+        }
+        self.percent_allowances.remove((*owner, *spender));
+        self.recurring_allowances.remove((*owner, *spender));
+        self.set_allowance(owner, spender, value); // 允许spender调用owner的value数字的金额
+        if value > 0 {
+          self.stamp_allowance_expiry(owner, spender);
+        }
+
+        self.env().emit_event(Approve {
+          from: *owner,
+          to: *spender,
+          value
+        });
+
+        Ok(())
+      }
+
+      pub fn transfer_helper(&mut self, from: &AccountId, to: &AccountId, value: Balance) -> Result<()> {
+        // Withdrawals out of the contract's escrows stay open while soulbound and under
+        // `TransfersOnly`.
+        let contract = self.env().account_id();
+        if !self.transferable && *from != contract {
+          return Err(Error::NonTransferable);
+        }
+        if self.breaker_pause_expired() {
+          self.pause_level = PauseLevel::None;
+          self.breaker_tripped = false;
+        }
+        match self.pause_level {
+          PauseLevel::Full => return Err(Error::Paused),
+          PauseLevel::TransfersOnly if *from != contract => return Err(Error::Paused),
+          _ => {}
+        }
+        if let Some(oracle) = self.gate_oracle {
+          let gate: ink::contract_ref!(TransferGate) = oracle.into();
+          if !gate.is_allowed() {
+            return Err(Error::GateClosed);
+          }
+        }
+        // Escrow moves in and out of the contract's own account; its final recipient
+        // is checked when the escrow is created.
+        if *from != contract && *to != contract {
+          self.ensure_registered(to)?;
+        }
+        if let Some(max) = self.max_transfers_per_account {
+          if *from != contract && self.sent_count.get(from).unwrap_or_default() >= max as u64 {
+            return Err(Error::TransferQuotaExceeded);
+          }
+        }
+
+        let balance_from = self.balance_of(*from);
+        let balance_to = self.balance_of(*to);
+
+        if value > balance_from {
+          return Err(Error::BalanceTooLow);
+        }
+        if balance_from - value < self.veto_locked(*from) {
+          return Err(Error::BalanceLocked);
+        }
+
+        self.set_balance(from, balance_from - value);
+        self.set_balance(to, balance_to + value);
+        self.record_outflow(from, to, value);
+
+        let sent = self.sent_count.get(from).unwrap_or_default();
+        self.sent_count.insert(from, &(sent + 1));
+        let received = self.received_count.get(to).unwrap_or_default();
+        self.received_count.insert(to, &(received + 1));
+
+        self.env().emit_event(Transfer {
+          from: Some(*from),
+          to: Some(*to),
+          value,
+        });
+
+        Ok(())
+      }
+
+      /// A transfer made by or for a holder, as opposed to an internal move such as a
+      /// sweep or payout: on top of `transfer_helper` it locks the forced savings share.
+      fn transfer_locking_savings(&mut self, from: &AccountId, to: &AccountId, value: Balance) -> Result<()> {
+        self.transfer_helper(from, to, value)?;
+        let contract = self.env().account_id();
+        if *from != contract && *to != contract {
+          self.lock_savings(to, value.saturating_mul(self.forced_savings_bps as Balance) / 10000);
+        }
+        Ok(())
+      }
+
+      /// Takes `value` off the allowance `from` gave `spender`.
+      fn spend_allowance(&mut self, from: &AccountId, spender: &AccountId, value: Balance) -> Result<()> {
+        let allowance = self.allowance(*from, *spender); // 获取允许sender调用from的金额
+        if allowance < value {
+          return Err(Error::AllowancesTooLow);
+        }
+
+        if let Some(mut recurring) = self.recurring_allowances.get((*from, *spender)) {
+          let period = self.recurring_period(&recurring);
+          if period != recurring.period {
+            recurring.period = period;
+            recurring.spent = 0;
+          }
+          recurring.spent += value;
+          self.recurring_allowances.insert((*from, *spender), &recurring);
+          self.touch_allowance(from, spender);
+        } else if !self.percent_allowances.contains((*from, *spender)) {
+          self.set_allowance(from, spender, allowance - value);
+        }
+
+        Ok(())
+      }
+
+      /// Moves `amount` of `account`'s fresh balance into the contract as savings.
+      fn lock_savings(&mut self, account: &AccountId, amount: Balance) {
+        if amount == 0 {
+          return;
+        }
+
+        let contract = self.env().account_id();
+        let balance = self.balance_of(*account);
+        self.set_balance(account, balance - amount);
+        let held = self.balance_of(contract);
+        self.set_balance(&contract, held + amount);
+
+        let now = self.env().block_timestamp();
+        let unlock_at = now.saturating_add(self.savings_lock_secs.saturating_mul(1000));
+        let mut savings = self.savings.get(account).unwrap_or_default();
+        if savings.newer_amount > 0 && now >= savings.unlock_at && now >= savings.newer_unlock_at {
+          savings.amount += savings.newer_amount;
+          savings.unlock_at = savings.unlock_at.max(savings.newer_unlock_at);
+          savings.newer_amount = 0;
+        }
+        if savings.amount == 0 {
+          savings.amount = amount;
+          savings.unlock_at = unlock_at;
+        } else {
+          savings.newer_amount += amount;
+          savings.newer_unlock_at = unlock_at;
+        }
+        self.savings.insert(account, &savings);
+        self.savings_total += amount;
+
+        self.env().emit_event(Transfer {
+          from: Some(*account),
+          to: Some(contract),
+          value: amount,
+        });
+      }
+    }
+
+    impl TERC20 for Erc20 {
+      #[ink(message)]
+      fn total_supply(&self) -> Balance {
+        self.total_supply
+      }
+
+      #[ink(message)]
+      fn balance_of(&self, who: AccountId) -> Balance {
+        self.balances.get(&who).unwrap_or_default()
+      }
+
+      #[ink(message)]
+      fn allowance(&self, owner: AccountId, spender: AccountId) -> Balance {
+        if let Some(expiry) = self.allowance_expiry.get((owner, spender)) {
+          if self.env().block_timestamp() >= expiry {
+            return 0;
+          }
+        }
+        if let Some(bps) = self.percent_allowances.get((owner, spender)) {
+          let dynamic = self.balance_of(owner).saturating_mul(bps as Balance) / 10000;
+          let dynamic = self.large_approval_threshold.map_or(dynamic, |threshold| dynamic.min(threshold));
+          return self.max_allowance_per_spender.map_or(dynamic, |ceiling| dynamic.min(ceiling));
+        }
+        if let Some(recurring) = self.recurring_allowances.get((owner, spender)) {
+          if self.recurring_period(&recurring) != recurring.period {
+            return recurring.per_period;
+          }
+          return recurring.per_period.saturating_sub(recurring.spent);
+        }
+        self.allowances.get(&(owner, spender)).unwrap_or_default()
+      }
+
+      #[ink(message)]
+      fn approve(&mut self, to: AccountId, value: Balance) -> Result<()> { // 允许谁动用多少资金
+        let sender = self.env().caller();
+        self.approve_helper(&sender, &to, value)
+      }
+
+      #[ink(message)]
+      fn transfer(&mut self, to: AccountId, value: Balance) -> Result<()> {
+        let sender = self.env().caller();
+        if let Some(threshold) = self.memo_required_above {
+          if value > threshold {
+            return Err(Error::MemoRequired);
+          }
+        }
+
+        return self.transfer_locking_savings(&sender, &to, value);
+      }
+
+      #[ink(message)]
+      fn transfer_from(&mut self, from: AccountId, to: AccountId, value: Balance) -> Result<()> {
+        let sender = self.env().caller();
+        self.spend_allowance(&from, &sender, value)?;
+
+        return self.transfer_locking_savings(&from, &to, value);
+      }
+
+      
+    }
+
+    /// Unit tests in Rust are normally defined within such a `#[cfg(test)]`
+    /// module and test functions are marked with a `#[test]` attribute.
+    /// The below code is technically just normal Rust code.
+    #[cfg(test)]
+    mod tests {
+      /// Imports all the definitions from the outer scope so we can use them here.
+      use super::*;
+      type Event = <Erc20 as ::ink::reflect::ContractEventBase>::Type;
+        /// We test if the default constructor does its job.
+      #[ink::test]
+      fn constructor_works() {
+        let erc20 = Erc20::new(10000);
+        let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+        assert_eq!(erc20.total_supply(), 10000);
+        assert_eq!(erc20.balance_of(accounts.alice), 10000);
+
+        let emitted_events = ink::env::test::recorded_events().collect::<Vec<_>>();
+
+        let event = &emitted_events[0];
+
+        let decode = <Event as scale::Decode>::decode(&mut &event.data[..]).expect("decode error");
+
+        match decode {
+          Event::Transfer(Transfer{ from, to, value }) => {
+            assert!(from.is_none(), "mint from error");
+            assert_eq!(to, Some(accounts.alice), "mint to error");
+            assert_eq!(value, 10000, "mint value error");
+          }
+          _ => panic!("match error"),
+        }
+      }
+
+      #[ink::test]
+      fn transfer_works() {
+        let mut erc20 = Erc20::new(10000);
+        let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+
+        let res = erc20.transfer(accounts.bob, 12);
+        assert!(res.is_ok());
+        assert_eq!(erc20.balance_of(accounts.alice), 10000 - 12);
+        assert_eq!(erc20.balance_of(accounts.bob), 12);
+      }
+
+      #[ink::test]
+      fn invalid_transfer_should_fail() {
+        let mut erc20 = Erc20::new(10000);
+        let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+        ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+
+        let res = erc20.transfer(accounts.charlie, 12);
+        assert!(res.is_err());
+        assert_eq!(res, Err(Error::BalanceTooLow));
+      }
+
+      #[ink::test]
+      fn config_reflects_setters() {
+        let mut erc20 = Erc20::new(10000);
+        let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+        assert_eq!(erc20.set_max_allowance_per_spender(Some(100)), Ok(()));
+        assert_eq!(erc20.set_memo_required_above(Some(200)), Ok(()));
+        assert_eq!(erc20.set_distribution_sink(Some(accounts.eve)), Ok(()));
+        assert_eq!(erc20.set_emission_schedule(accounts.bob, 3, 1000), Ok(()));
+        assert_eq!(erc20.set_mint_queue_delay(86400), Ok(()));
+        assert_eq!(erc20.set_circuit_breaker(Some(300), 7), Ok(()));
+        assert_eq!(erc20.set_mint_approval_threshold(Some(400)), Ok(()));
+        assert_eq!(erc20.set_approvers(vec![accounts.charlie, accounts.django]), Ok(()));
+        assert_eq!(erc20.set_default_allowance_ttl(Some(3600)), Ok(()));
+        assert_eq!(erc20.set_min_mint_interval(Some(60)), Ok(()));
+        assert_eq!(erc20.set_burn_governance(500, 120), Ok(()));
+        assert_eq!(erc20.set_forced_savings(1000, 30), Ok(()));
+        assert_eq!(erc20.set_large_approval_threshold(Some(5000)), Ok(()));
+        assert_eq!(erc20.set_max_transfers_per_account(Some(9)), Ok(()));
+        assert_eq!(erc20.set_supply_cap(Some(20000), 1000), Ok(()));
+
+        assert_eq!(erc20.config(), Config {
+          owner: accounts.alice,
+          pause_level: PauseLevel::None,
+          max_allowance_per_spender: Some(100),
+          memo_required_above: Some(200),
+          distribution_sink: Some(accounts.eve),
+          emission: Some(EmissionSchedule { recipient: accounts.bob, per_sec: 3, end: 1000, last_emitted: 0 }),
+          breaker_threshold: Some(300),
+          breaker_window_blocks: 7,
+          mint_approval_threshold: Some(400),
+          mint_approvers: vec![accounts.charlie, accounts.django],
+          default_allowance_ttl: Some(3600),
+          min_mint_interval_secs: Some(60),
+          mint_queue_delay_secs: 86400,
+          transferable: true,
+          require_registration: false,
+          distribution_interval_secs: 0,
+          burn_veto_quorum: 500,
+          burn_delay_secs: 120,
+          forced_savings_bps: 1000,
+          savings_lock_secs: 30,
+          gate_oracle: None,
+          large_approval_threshold: Some(5000),
+          max_transfers_per_account: Some(9),
+          supply_cap: Some(SupplyCap { base: 20000, growth_per_year: 1000, start: 0 }),
+        });
+      }
+
+      #[ink::test]
+      fn can_execute_reflects_permissions() {
+        let mut erc20 = Erc20::new(10000);
+        let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+        assert_eq!(erc20.set_approvers(vec![accounts.bob]), Ok(()));
+
+        assert!(erc20.can_execute(ActionKind::Mint));
+        assert!(erc20.can_execute(ActionKind::SetCircuitBreaker));
+        assert!(!erc20.can_execute(ActionKind::ApproveMint));
+
+        ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+        assert!(!erc20.can_execute(ActionKind::Mint));
+        assert!(!erc20.can_execute(ActionKind::SetCircuitBreaker));
+        assert!(erc20.can_execute(ActionKind::ApproveMint));
+
+        ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+        assert_eq!(erc20.renounce_ownership(), Ok(()));
+        assert!(!erc20.can_execute(ActionKind::Mint));
+      }
+
+      #[ink::test]
+      fn allowance_ceiling_works() {
+        let mut erc20 = Erc20::new(10000);
+        let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+
+        assert_eq!(erc20.set_max_allowance_per_spender(Some(100)), Ok(()));
+        assert_eq!(erc20.approve(accounts.bob, 100), Ok(()));
+        assert_eq!(erc20.approve(accounts.bob, 101), Err(Error::AllowanceCeilingExceeded));
+
+        ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+        assert_eq!(erc20.transfer_from(accounts.alice, accounts.bob, 100), Ok(()));
+        assert_eq!(erc20.set_max_allowance_per_spender(None), Err(Error::NotOwner));
+      }
+
+      #[ink::test]
+      fn renounce_ownership_works() {
+        let mut erc20 = Erc20::new(10000);
+
+        assert_eq!(erc20.renounce_ownership(), Ok(()));
+        assert_eq!(erc20.owner(), AccountId::from([0x0; 32]));
+        assert_eq!(erc20.set_max_allowance_per_spender(Some(1)), Err(Error::NotOwner));
+        assert_eq!(erc20.renounce_ownership(), Err(Error::NotOwner));
+
+        ink::env::test::set_caller::<ink::env::DefaultEnvironment>(AccountId::from([0x0; 32]));
+        assert_eq!(erc20.set_max_allowance_per_spender(Some(1)), Err(Error::NotOwner));
+      }
+
+      #[ink::test]
+      fn cancel_delayed_transfer_works() {
+        let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+        ink::env::test::set_callee::<ink::env::DefaultEnvironment>(accounts.django);
+        let mut erc20 = Erc20::new(10000);
+
+        let id = erc20.transfer_delayed(accounts.bob, 100, 60).expect("escrow failed");
+        assert_eq!(erc20.balance_of(accounts.alice), 10000 - 100);
+        assert_eq!(erc20.balance_of(accounts.django), 100);
+
+        ink::env::test::set_block_timestamp::<ink::env::DefaultEnvironment>(59_999);
+        ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+        assert_eq!(erc20.claim_transfer(id), Err(Error::TransferStillPending));
+        assert_eq!(erc20.cancel_transfer(id), Err(Error::NotAllowed));
+
+        ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+        assert_eq!(erc20.cancel_transfer(id), Ok(()));
+        assert_eq!(erc20.balance_of(accounts.alice), 10000);
+        assert_eq!(erc20.balance_of(accounts.django), 0);
+        assert_eq!(erc20.delayed_transfer(id), None);
+        assert_eq!(erc20.cancel_transfer(id), Err(Error::DelayedTransferNotFound));
+      }
+
+      #[ink::test]
+      fn claim_delayed_transfer_works() {
+        let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+        ink::env::test::set_callee::<ink::env::DefaultEnvironment>(accounts.django);
+        let mut erc20 = Erc20::new(10000);
+
+        let id = erc20.transfer_delayed(accounts.bob, 100, 60).expect("escrow failed");
+
+        ink::env::test::set_block_timestamp::<ink::env::DefaultEnvironment>(60_000);
+        assert_eq!(erc20.cancel_transfer(id), Err(Error::CancelWindowElapsed));
+        assert_eq!(erc20.claim_transfer(id), Ok(()));
+        assert_eq!(erc20.balance_of(accounts.bob), 100);
+        assert_eq!(erc20.balance_of(accounts.django), 0);
+        assert_eq!(erc20.claim_transfer(id), Err(Error::DelayedTransferNotFound));
+      }
+
+      #[ink::test]
+      fn escrow_to_the_contract_is_rejected() {
+        let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+        ink::env::test::set_callee::<ink::env::DefaultEnvironment>(accounts.django);
+        let mut erc20 = Erc20::new(10000);
+        assert_eq!(erc20.transfer_delayed(accounts.django, 100, 60), Err(Error::NotAllowed));
+        assert_eq!(erc20.transfer_claimable(accounts.django, 100, 60_000), Err(Error::NotAllowed));
+        assert_eq!(erc20.balance_of(accounts.django), 0);
+      }
+
+      #[ink::test]
+      fn claim_before_expiry_works() {
+        let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+        ink::env::test::set_callee::<ink::env::DefaultEnvironment>(accounts.django);
+        let mut erc20 = Erc20::new(10000);
+
+        let id = erc20.transfer_claimable(accounts.bob, 100, 60_000).expect("escrow failed");
+        assert_eq!(erc20.balance_of(accounts.django), 100);
+        assert_eq!(erc20.claim(id), Err(Error::NotAllowed));
+        assert_eq!(erc20.reclaim_expired(id), Err(Error::NotExpired));
+
+        ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+        assert_eq!(erc20.claim(id), Ok(()));
+        assert_eq!(erc20.balance_of(accounts.bob), 100);
+        assert_eq!(erc20.balance_of(accounts.django), 0);
+        assert_eq!(erc20.claim(id), Err(Error::ClaimableTransferNotFound));
+      }
+
+      #[ink::test]
+      fn reclaim_after_expiry_works() {
+        let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+        ink::env::test::set_callee::<ink::env::DefaultEnvironment>(accounts.django);
+        let mut erc20 = Erc20::new(10000);
+
+        let id = erc20.transfer_claimable(accounts.bob, 100, 60_000).expect("escrow failed");
+        ink::env::test::set_block_timestamp::<ink::env::DefaultEnvironment>(60_000);
+        assert_eq!(erc20.transfer_claimable(accounts.bob, 100, 60_000), Err(Error::ClaimExpired));
+
+        ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+        assert_eq!(erc20.claim(id), Err(Error::ClaimExpired));
+        assert_eq!(erc20.reclaim_expired(id), Err(Error::NotAllowed));
+
+        ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+        assert_eq!(erc20.reclaim_expired(id), Ok(()));
+        assert_eq!(erc20.balance_of(accounts.alice), 10000);
+        assert_eq!(erc20.claimable_transfer(id), None);
+      }
+
+      #[ink::test]
+      fn now_matches_block_timestamp() {
+        let erc20 = Erc20::new(10000);
+        ink::env::test::set_block_timestamp::<ink::env::DefaultEnvironment>(1_700_000_000_000);
+        assert_eq!(erc20.now(), 1_700_000_000_000);
+      }
+
+      #[ink::test]
+      fn emission_schedule_mints_elapsed_emission() {
+        let mut erc20 = Erc20::new(10000);
+        let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+        assert_eq!(erc20.emit_tokens(), Ok(0));
+        assert_eq!(erc20.set_emission_schedule(accounts.bob, 10, 100_000), Ok(()));
+
+        ink::env::test::set_block_timestamp::<ink::env::DefaultEnvironment>(30_500);
+        ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+        assert_eq!(erc20.emit_tokens(), Ok(300));
+        assert_eq!(erc20.balance_of(accounts.bob), 300);
+        assert_eq!(erc20.total_supply(), 10300);
+
+        // Emission stops at `emission_end`, including the half second carried over.
+        ink::env::test::set_block_timestamp::<ink::env::DefaultEnvironment>(250_000);
+        assert_eq!(erc20.emit_tokens(), Ok(700));
+        assert_eq!(erc20.emit_tokens(), Ok(0));
+        assert_eq!(erc20.balance_of(accounts.bob), 1000);
+        assert_eq!(erc20.total_supply(), 11000);
+
+        assert_eq!(erc20.set_emission_schedule(accounts.charlie, 1, 300_000), Err(Error::NotOwner));
+      }
+
+      #[ink::test]
+      fn circuit_breaker_trips_on_rapid_drain() {
+        let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+        ink::env::test::set_callee::<ink::env::DefaultEnvironment>(accounts.django);
+        let mut erc20 = Erc20::new(10000);
+        assert_eq!(erc20.set_circuit_breaker(Some(1000), 10), Ok(()));
+
+        assert_eq!(erc20.transfer(accounts.bob, 600), Ok(()));
+        // A new window starts after 10 blocks, so this does not add up with the first.
+        for _ in 0..10 {
+          ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+        }
+        assert_eq!(erc20.transfer(accounts.bob, 600), Ok(()));
+        assert!(!erc20.paused());
+
+        assert_eq!(erc20.transfer(accounts.bob, 600), Ok(()));
+        assert!(erc20.paused());
+        assert_eq!(erc20.transfer(accounts.bob, 1), Err(Error::Paused));
+
+        ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+        assert_eq!(erc20.approve(accounts.charlie, 100), Ok(()));
+        ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+        assert_eq!(erc20.transfer_from(accounts.bob, accounts.charlie, 100), Err(Error::Paused));
+        assert_eq!(erc20.reset_circuit_breaker(), Err(Error::NotOwner));
+
+        ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+        assert_eq!(erc20.reset_circuit_breaker(), Ok(()));
+        assert_eq!(erc20.transfer(accounts.bob, 1), Ok(()));
+        assert_eq!(erc20.balance_of(accounts.bob), 1801);
+      }
+
+      #[ink::test]
+      fn transfer_stats_count_both_sides() {
+        let mut erc20 = Erc20::new(10000);
+        let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+        assert_eq!(erc20.transfer_stats(accounts.alice), (0, 0));
+
+        assert_eq!(erc20.transfer(accounts.bob, 10), Ok(()));
+        assert_eq!(erc20.transfer(accounts.bob, 10), Ok(()));
+        ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+        assert_eq!(erc20.transfer(accounts.alice, 5), Ok(()));
+        assert_eq!(erc20.transfer(accounts.bob, 100), Err(Error::BalanceTooLow));
+
+        assert_eq!(erc20.transfer_stats(accounts.alice), (2, 1));
+        assert_eq!(erc20.transfer_stats(accounts.bob), (1, 2));
+      }
+
+      #[ink::test]
+      fn mint_works() {
+        let mut erc20 = Erc20::new(10000);
+        let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+
+        assert_eq!(erc20.mint(accounts.bob, 500), Ok(()));
+        assert_eq!(erc20.balance_of(accounts.bob), 500);
+        assert_eq!(erc20.total_supply(), 10500);
+
+        ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+        assert_eq!(erc20.mint(accounts.bob, 500), Err(Error::NotOwner));
+      }
+
+      #[ink::test]
+      fn burn_works() {
+        let mut erc20 = Erc20::new(10000);
+        let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+
+        assert_eq!(erc20.burn(400), Ok(()));
+        assert_eq!(erc20.balance_of(accounts.alice), 9600);
+        assert_eq!(erc20.total_supply(), 9600);
+        assert_eq!(erc20.burn(9601), Err(Error::BalanceTooLow));
+      }
+
+      #[ink::test]
+      fn recurring_allowance_refreshes_each_period() {
+        let mut erc20 = Erc20::new(10000);
+        let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+        assert_eq!(erc20.approve_recurring(accounts.bob, 100, 0), Err(Error::InvalidPeriod));
+        assert_eq!(erc20.approve_recurring(accounts.bob, 100, 3600), Ok(()));
+
+        ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+        assert_eq!(erc20.transfer_from(accounts.alice, accounts.charlie, 60), Ok(()));
+        assert_eq!(erc20.allowance(accounts.alice, accounts.bob), 40);
+        assert_eq!(erc20.transfer_from(accounts.alice, accounts.charlie, 50), Err(Error::AllowancesTooLow));
+
+        ink::env::test::set_block_timestamp::<ink::env::DefaultEnvironment>(3_600_000);
+        assert_eq!(erc20.allowance(accounts.alice, accounts.bob), 100);
+        assert_eq!(erc20.transfer_from(accounts.alice, accounts.charlie, 100), Ok(()));
+        assert_eq!(erc20.allowance(accounts.alice, accounts.bob), 0);
+        assert_eq!(erc20.balance_of(accounts.charlie), 160);
+
+        ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+        assert_eq!(erc20.approve(accounts.bob, 5), Ok(()));
+        assert_eq!(erc20.recurring_allowance(accounts.alice, accounts.bob), None);
+        assert_eq!(erc20.allowance(accounts.alice, accounts.bob), 5);
+      }
+
+      #[ink::test]
+      fn forced_savings_lock_part_of_transfers() {
+        let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+        ink::env::test::set_callee::<ink::env::DefaultEnvironment>(accounts.django);
+        let mut erc20 = Erc20::new(10000);
+        assert_eq!(erc20.set_forced_savings(10001, 60), Err(Error::InvalidBasisPoints));
+        assert_eq!(erc20.set_forced_savings(2000, 60), Ok(()));
+
+        assert_eq!(erc20.transfer(accounts.bob, 1000), Ok(()));
+        assert_eq!(erc20.balance_of(accounts.bob), 800);
+        assert_eq!(erc20.savings_of(accounts.bob), 200);
+        assert_eq!(erc20.balance_of(accounts.django), 200);
+
+        ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+        assert_eq!(erc20.withdraw_savings(), Err(Error::SavingsLocked));
+
+        ink::env::test::set_block_timestamp::<ink::env::DefaultEnvironment>(60_000);
+        assert_eq!(erc20.withdraw_savings(), Ok(()));
+        assert_eq!(erc20.balance_of(accounts.bob), 1000);
+        assert_eq!(erc20.savings_of(accounts.bob), 0);
+        assert_eq!(erc20.balance_of(accounts.django), 0);
+        assert_eq!(erc20.withdraw_savings(), Err(Error::BalanceTooLow));
+      }
+
+      #[ink::test]
+      fn effective_supply_excludes_dead_tokens() {
+        let mut erc20 = Erc20::new(10000);
+        let dead = AccountId::from([0x0; 32]);
+        assert_eq!(erc20.effective_supply(), 10000);
+
+        assert_eq!(erc20.transfer(dead, 300), Ok(()));
+        assert_eq!(erc20.transfer(dead, 200), Ok(()));
+        assert_eq!(erc20.total_supply(), 10000);
+        assert_eq!(erc20.effective_supply(), 9500);
+      }
+
+      #[ink::test]
+      fn large_approvals_need_confirmation() {
+        let mut erc20 = Erc20::new(10000);
+        let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+        assert_eq!(erc20.set_large_approval_threshold(Some(1000)), Ok(()));
+
+        assert_eq!(erc20.approve(accounts.bob, 1000), Ok(()));
+        assert_eq!(erc20.allowance(accounts.alice, accounts.bob), 1000);
+
+        assert_eq!(erc20.approve(accounts.bob, 5000), Ok(()));
+        assert_eq!(erc20.allowance(accounts.alice, accounts.bob), 1000);
+        assert_eq!(erc20.pending_large_approval(accounts.alice, accounts.bob), Some(5000));
+
+        ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+        assert_eq!(erc20.transfer_from(accounts.alice, accounts.charlie, 5000), Err(Error::AllowancesTooLow));
+        assert_eq!(erc20.confirm_large_approval(accounts.alice), Err(Error::NoPendingApproval));
+
+        ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+        assert_eq!(erc20.confirm_large_approval(accounts.bob), Ok(()));
+        assert_eq!(erc20.pending_large_approval(accounts.alice, accounts.bob), None);
+
+        ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+        assert_eq!(erc20.transfer_from(accounts.alice, accounts.charlie, 5000), Ok(()));
+        assert_eq!(erc20.balance_of(accounts.charlie), 5000);
+      }
+
+      #[ink::test]
+      fn large_approval_threshold_covers_percent_and_recurring() {
+        let mut erc20 = Erc20::new(10000);
+        let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+        assert_eq!(erc20.set_large_approval_threshold(Some(1000)), Ok(()));
+
+        assert_eq!(erc20.approve_percent(accounts.bob, 1001), Err(Error::ApprovalTooLarge));
+        assert_eq!(erc20.approve_percent(accounts.bob, 1000), Ok(()));
+        assert_eq!(erc20.approve_recurring(accounts.charlie, 1001, 60), Err(Error::ApprovalTooLarge));
+        assert_eq!(erc20.approve_recurring(accounts.charlie, 1000, 60), Ok(()));
+        assert!(erc20.recurring_allowance(accounts.alice, accounts.charlie).is_some());
+
+        ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.eve);
+        assert_eq!(erc20.approve_percent(accounts.bob, 10000), Ok(()));
+        ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+        assert_eq!(erc20.transfer(accounts.eve, 5000), Ok(()));
+        assert_eq!(erc20.allowance(accounts.eve, accounts.bob), 1000);
+      }
+
+      #[ink::test]
+      fn consolidate_sweeps_authorized_balances() {
+        let mut erc20 = Erc20::new(10000);
+        let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+        let froms = vec![accounts.bob, accounts.charlie, accounts.django];
+        for (from, value) in froms.iter().zip([100, 200, 300]) {
+          assert_eq!(erc20.transfer(*from, value), Ok(()));
+          ink::env::test::set_caller::<ink::env::DefaultEnvironment>(*from);
+          assert_eq!(erc20.approve(accounts.alice, value), Ok(()));
+          ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+        }
+
+        ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+        assert_eq!(erc20.consolidate(froms.clone(), accounts.eve), Err(Error::NotOwner));
+
+        ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+        assert_eq!(erc20.consolidate(vec![accounts.frank; MAX_CONSOLIDATE + 1], accounts.eve), Err(Error::BatchTooLarge));
+        assert_eq!(erc20.consolidate(froms.clone(), accounts.eve), Ok(()));
+        assert_eq!(erc20.balance_of(accounts.eve), 600);
+        for from in froms {
+          assert_eq!(erc20.balance_of(from), 0);
+        }
+      }
+
+      #[ink::test]
+      fn transfer_quota_limits_sends() {
+        let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+        ink::env::test::set_callee::<ink::env::DefaultEnvironment>(accounts.django);
+        let mut erc20 = Erc20::new(10000);
+        assert_eq!(erc20.transfer(accounts.bob, 100), Ok(()));
+        assert_eq!(erc20.set_max_transfers_per_account(Some(2)), Ok(()));
+
+        assert_eq!(erc20.transfer(accounts.bob, 100), Ok(()));
+        assert_eq!(erc20.transfer(accounts.bob, 100), Err(Error::TransferQuotaExceeded));
+
+        ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+        assert_eq!(erc20.transfer(accounts.charlie, 10), Ok(()));
+        assert_eq!(erc20.transfer(accounts.charlie, 10), Ok(()));
+        assert_eq!(erc20.transfer(accounts.charlie, 10), Err(Error::TransferQuotaExceeded));
+        assert_eq!(erc20.balance_of(accounts.bob), 180);
+      }
+
+      #[ink::test]
+      fn supply_cap_expands_over_time() {
+        let mut erc20 = Erc20::new(10000);
+        let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+        assert_eq!(erc20.current_cap(), None);
+        assert_eq!(erc20.set_supply_cap(Some(12000), 4000), Ok(()));
+        assert_eq!(erc20.current_cap(), Some(12000));
+
+        assert_eq!(erc20.mint(accounts.bob, 2000), Ok(()));
+        assert_eq!(erc20.mint(accounts.bob, 1), Err(Error::CapExceeded));
+
+        ink::env::test::set_block_timestamp::<ink::env::DefaultEnvironment>(MILLIS_PER_YEAR / 4);
+        assert_eq!(erc20.current_cap(), Some(13000));
+        assert_eq!(erc20.mint(accounts.bob, 1001), Err(Error::CapExceeded));
+        assert_eq!(erc20.mint(accounts.bob, 1000), Ok(()));
+
+        ink::env::test::set_block_timestamp::<ink::env::DefaultEnvironment>(2 * MILLIS_PER_YEAR);
+        assert_eq!(erc20.current_cap(), Some(20000));
+        assert_eq!(erc20.mint(accounts.bob, 7000), Ok(()));
+        assert_eq!(erc20.total_supply(), 20000);
+      }
+
+      #[ink::test]
+      fn rank_of_follows_balances() {
+        let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+        ink::env::test::set_callee::<ink::env::DefaultEnvironment>(accounts.frank);
+        let mut erc20 = Erc20::new(10000);
+        assert_eq!(erc20.transfer(accounts.bob, 3000), Ok(()));
+        assert_eq!(erc20.transfer(accounts.charlie, 2000), Ok(()));
+
+        assert_eq!(erc20.rank_of(accounts.alice), Some(1));
+        assert_eq!(erc20.rank_of(accounts.bob), Some(2));
+        assert_eq!(erc20.rank_of(accounts.charlie), Some(3));
+        assert_eq!(erc20.rank_of(accounts.django), None);
+
+        ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+        assert_eq!(erc20.transfer(accounts.charlie, 1500), Ok(()));
+        assert_eq!(erc20.rank_of(accounts.charlie), Some(2));
+        assert_eq!(erc20.rank_of(accounts.bob), Some(3));
+      }
+
+      #[ink::test]
+      fn circuit_breaker_counts_net_outflow_and_lifts_itself() {
+        let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+        ink::env::test::set_callee::<ink::env::DefaultEnvironment>(accounts.django);
+        let mut erc20 = Erc20::new(10000);
+        assert_eq!(erc20.transfer(accounts.bob, 100), Ok(()));
+        assert_eq!(erc20.set_circuit_breaker(Some(500), 10), Ok(()));
+
+        for _ in 0..10 {
+          ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+          assert_eq!(erc20.transfer(accounts.charlie, 100), Ok(()));
+          ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+          assert_eq!(erc20.transfer(accounts.bob, 100), Ok(()));
+        }
+        assert!(!erc20.paused());
+
+        ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+        assert_eq!(erc20.transfer(accounts.eve, 501), Ok(()));
+        assert_eq!(erc20.pause_level(), PauseLevel::TransfersOnly);
+        assert_eq!(erc20.transfer(accounts.eve, 1), Err(Error::Paused));
+
+        for _ in 0..10 {
+          ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+        }
+        assert_eq!(erc20.pause_level(), PauseLevel::None);
+        assert_eq!(erc20.transfer(accounts.eve, 1), Ok(()));
+        assert_eq!(erc20.balance_of(accounts.eve), 502);
+      }
+
+      #[ink::test]
+      fn breaker_reset_keeps_manual_pause() {
+        let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+        ink::env::test::set_callee::<ink::env::DefaultEnvironment>(accounts.django);
+        let mut erc20 = Erc20::new(10000);
+        assert_eq!(erc20.set_pause_level(PauseLevel::Full), Ok(()));
+        assert_eq!(erc20.reset_circuit_breaker(), Ok(()));
+        assert_eq!(erc20.pause_level(), PauseLevel::Full);
+        assert_eq!(erc20.transfer(accounts.bob, 1), Err(Error::Paused));
+
+        assert_eq!(erc20.set_pause_level(PauseLevel::None), Ok(()));
+        assert_eq!(erc20.set_circuit_breaker(Some(100), 10), Ok(()));
+        assert_eq!(erc20.transfer(accounts.bob, 101), Ok(()));
+        assert_eq!(erc20.pause_level(), PauseLevel::TransfersOnly);
+        assert_eq!(erc20.set_pause_level(PauseLevel::Full), Ok(()));
+        assert_eq!(erc20.reset_circuit_breaker(), Ok(()));
+        assert_eq!(erc20.pause_level(), PauseLevel::Full);
+      }
+
+      #[ink::test]
+      fn transfers_only_pause_keeps_exits_open() {
+        let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+        ink::env::test::set_callee::<ink::env::DefaultEnvironment>(accounts.django);
+        let mut erc20 = Erc20::new(10000);
+        let id = erc20.transfer_claimable(accounts.bob, 500, 3600).expect("escrow failed");
+
+        ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+        assert_eq!(erc20.set_pause_level(PauseLevel::Full), Err(Error::NotOwner));
+
+        ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+        assert_eq!(erc20.set_pause_level(PauseLevel::TransfersOnly), Ok(()));
+        assert!(erc20.paused());
+        assert_eq!(erc20.transfer(accounts.bob, 100), Err(Error::Paused));
+        assert_eq!(erc20.burn(100), Ok(()));
+
+        ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+        assert_eq!(erc20.claim(id), Ok(()));
+        assert_eq!(erc20.balance_of(accounts.bob), 500);
+
+        ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+        assert_eq!(erc20.set_pause_level(PauseLevel::Full), Ok(()));
+        assert_eq!(erc20.burn(100), Err(Error::Paused));
+        assert_eq!(erc20.set_pause_level(PauseLevel::None), Ok(()));
+        assert_eq!(erc20.transfer(accounts.bob, 100), Ok(()));
+        assert_eq!(erc20.total_supply(), 9900);
+      }
+
+      #[ink::test]
+      fn total_value_locked_sums_escrows_and_savings() {
+        let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+        ink::env::test::set_callee::<ink::env::DefaultEnvironment>(accounts.django);
+        let mut erc20 = Erc20::new(10000);
+        assert_eq!(erc20.set_forced_savings(1000, 60), Ok(()));
+        assert_eq!(erc20.set_burn_governance(5000, 60), Ok(()));
+
+        let delayed = erc20.transfer_delayed(accounts.bob, 100, 60).expect("delayed transfer failed");
+        let claimable = erc20.transfer_claimable(accounts.charlie, 200, 3600).expect("escrow failed");
+        erc20.propose_burn(300).expect("proposal failed");
+        assert_eq!(erc20.transfer(accounts.eve, 1000), Ok(()));
+        assert_eq!(erc20.total_value_locked(), 100 + 200 + 300 + 100);
+        assert_eq!(erc20.total_value_locked(), erc20.balance_of(accounts.django));
+
+        assert_eq!(erc20.cancel_transfer(delayed), Ok(()));
+        ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+        assert_eq!(erc20.claim(claimable), Ok(()));
+        assert_eq!(erc20.total_value_locked(), 300 + 100);
+
+        ink::env::test::set_block_timestamp::<ink::env::DefaultEnvironment>(60_000);
+        ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.eve);
+        assert_eq!(erc20.withdraw_savings(), Ok(()));
+        assert_eq!(erc20.total_value_locked(), 300);
+      }
+
+      /// An ECDSA key and the account it controls, derived the way the contract does.
+      fn signer(seed: u8) -> (secp256k1::SecretKey, AccountId) {
+        let secret = secp256k1::SecretKey::from_slice(&[seed; 32]).expect("invalid key");
+        let public_key = secp256k1::PublicKey::from_secret_key(secp256k1::SECP256K1, &secret).serialize();
+        let mut account = [0u8; 32];
+        ink::env::hash_bytes::<Blake2x256>(&public_key, &mut account);
+        (secret, AccountId::from(account))
+      }
+
+      fn sign(secret: &secp256k1::SecretKey, hash: [u8; 32]) -> [u8; 65] {
+        let message = secp256k1::Message::from_slice(&hash).expect("invalid hash");
+        let (recovery_id, compact) = secp256k1::SECP256K1.sign_ecdsa_recoverable(&message, secret).serialize_compact();
+        let mut signature = [0u8; 65];
+        signature[..64].copy_from_slice(&compact);
+        signature[64] = recovery_id.to_i32() as u8;
+        signature
+      }
+
+      #[ink::test]
+      fn signed_batch_executes_once() {
+        let mut erc20 = Erc20::new(10000);
+        let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+        let (secret, owner) = signer(0x11);
+        assert_eq!(erc20.transfer(owner, 1000), Ok(()));
+
+        let ops = vec![
+          Op::Transfer { to: accounts.bob, value: 300 },
+          Op::Approve { spender: accounts.charlie, value: 50 },
+          Op::Burn { value: 100 },
+        ];
+        let signature = sign(&secret, erc20.batch_signing_hash(owner, ops.clone(), 0, 1000));
+
+        ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.eve);
+        let mut tampered = ops.clone();
+        tampered[0] = Op::Transfer { to: accounts.eve, value: 300 };
+        assert_eq!(erc20.execute_batch_by_sig(owner, tampered, 0, 1000, signature), Err(Error::InvalidSignature));
+        assert_eq!(erc20.execute_batch_by_sig(owner, ops.clone(), 1, 1000, signature), Err(Error::InvalidNonce));
+
+        assert_eq!(erc20.execute_batch_by_sig(owner, ops.clone(), 0, 1000, signature), Ok(()));
+        assert_eq!(erc20.balance_of(owner), 600);
+        assert_eq!(erc20.balance_of(accounts.bob), 300);
+        assert_eq!(erc20.allowance(owner, accounts.charlie), 50);
+        assert_eq!(erc20.total_supply(), 9900);
+        assert_eq!(erc20.sig_nonce(owner), 1);
+        assert_eq!(erc20.execute_batch_by_sig(owner, ops.clone(), 0, 1000, signature), Err(Error::InvalidNonce));
+
+        let signature = sign(&secret, erc20.batch_signing_hash(owner, ops.clone(), 1, 1000));
+        ink::env::test::set_block_timestamp::<ink::env::DefaultEnvironment>(1001);
+        assert_eq!(erc20.execute_batch_by_sig(owner, ops, 1, 1000, signature), Err(Error::SignatureExpired));
+      }
+
+      #[ink::test]
+      fn savings_deposits_do_not_extend_older_locks() {
+        let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+        ink::env::test::set_callee::<ink::env::DefaultEnvironment>(accounts.django);
+        let mut erc20 = Erc20::new(10000);
+        assert_eq!(erc20.set_forced_savings(5000, 60), Ok(()));
+        assert_eq!(erc20.transfer(accounts.bob, 1000), Ok(()));
+
+        ink::env::test::set_block_timestamp::<ink::env::DefaultEnvironment>(59_000);
+        assert_eq!(erc20.transfer(accounts.bob, 10), Ok(()));
+        assert_eq!(erc20.savings_of(accounts.bob), 505);
+
+        ink::env::test::set_block_timestamp::<ink::env::DefaultEnvironment>(60_000);
+        ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+        assert_eq!(erc20.withdraw_savings(), Ok(()));
+        assert_eq!(erc20.balance_of(accounts.bob), 1005);
+        assert_eq!(erc20.savings_of(accounts.bob), 5);
+        assert_eq!(erc20.withdraw_savings(), Err(Error::SavingsLocked));
+
+        ink::env::test::set_block_timestamp::<ink::env::DefaultEnvironment>(119_000);
+        assert_eq!(erc20.withdraw_savings(), Ok(()));
+        assert_eq!(erc20.savings(accounts.bob), None);
+      }
+
+      #[ink::test]
+      fn forced_savings_skip_internal_moves() {
+        let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+        ink::env::test::set_callee::<ink::env::DefaultEnvironment>(accounts.django);
+        let mut erc20 = Erc20::new(10000);
+        assert_eq!(erc20.transfer(accounts.bob, 100), Ok(()));
+        ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+        assert_eq!(erc20.approve(accounts.alice, 100), Ok(()));
+
+        ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+        assert_eq!(erc20.set_forced_savings(5000, 60), Ok(()));
+        assert_eq!(erc20.consolidate(vec![accounts.bob], accounts.eve), Ok(()));
+        assert_eq!(erc20.balance_of(accounts.eve), 100);
+        assert_eq!(erc20.savings_of(accounts.eve), 0);
+      }
+
+      #[ink::test]
+      fn vetoed_burn_is_refunded() {
+        let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+        ink::env::test::set_callee::<ink::env::DefaultEnvironment>(accounts.frank);
+        let mut erc20 = Erc20::new(10000);
+        assert_eq!(erc20.transfer(accounts.bob, 2000), Ok(()));
+        assert_eq!(erc20.transfer(accounts.charlie, 2000), Ok(()));
+        assert_eq!(erc20.set_burn_governance(3000, 3600), Ok(()));
+
+        let id = erc20.propose_burn(1000).expect("proposal failed");
+        assert_eq!(erc20.balance_of(accounts.alice), 5000);
+
+        ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+        assert_eq!(erc20.veto_burn(id), Ok(()));
+        assert_eq!(erc20.veto_burn(id), Err(Error::AlreadyVetoed));
+        assert_eq!(erc20.burn_proposal(id).map(|proposal| proposal.veto_weight), Some(2000));
+
+        ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+        assert_eq!(erc20.veto_burn(id), Ok(()));
+        assert_eq!(erc20.burn_proposal(id), None);
+        assert_eq!(erc20.balance_of(accounts.alice), 6000);
+        assert_eq!(erc20.total_supply(), 10000);
+        assert_eq!(erc20.finalize_burn(id), Err(Error::BurnProposalNotFound));
+      }
+
+      #[ink::test]
+      fn vetoes_need_weight_and_lock_it() {
+        let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+        ink::env::test::set_callee::<ink::env::DefaultEnvironment>(accounts.frank);
+        let mut erc20 = Erc20::new(10000);
+        assert_eq!(erc20.set_burn_governance(0, 60), Err(Error::InvalidQuorum));
+        assert_eq!(erc20.set_burn_governance(3000, 60), Ok(()));
+        assert_eq!(erc20.transfer(accounts.bob, 2000), Ok(()));
+        let id = erc20.propose_burn(1000).expect("proposal failed");
+
+        ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.eve);
+        assert_eq!(erc20.veto_burn(id), Err(Error::BalanceTooLow));
+
+        ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+        assert_eq!(erc20.veto_burn(id), Ok(()));
+        assert_eq!(erc20.veto_locked(accounts.bob), 2000);
+        assert_eq!(erc20.transfer(accounts.charlie, 2000), Err(Error::BalanceLocked));
+        assert_eq!(erc20.burn(1), Err(Error::BalanceLocked));
+
+        ink::env::test::set_block_timestamp::<ink::env::DefaultEnvironment>(60_000);
+        assert_eq!(erc20.veto_locked(accounts.bob), 0);
+        assert_eq!(erc20.transfer(accounts.charlie, 2000), Ok(()));
+        assert_eq!(erc20.burn_proposal(id).map(|proposal| proposal.veto_weight), Some(2000));
+      }
+
+      #[ink::test]
+      fn unvetoed_burn_finalizes_after_delay() {
+        let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+        ink::env::test::set_callee::<ink::env::DefaultEnvironment>(accounts.frank);
+        let mut erc20 = Erc20::new(10000);
+        assert_eq!(erc20.transfer(accounts.bob, 2000), Ok(()));
+        assert_eq!(erc20.set_burn_governance(3000, 3600), Ok(()));
+
+        let id = erc20.propose_burn(1000).expect("proposal failed");
+        ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+        assert_eq!(erc20.veto_burn(id), Ok(()));
+        assert_eq!(erc20.finalize_burn(id), Err(Error::BurnStillPending));
+
+        ink::env::test::set_block_timestamp::<ink::env::DefaultEnvironment>(3_600_000);
+        ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+        assert_eq!(erc20.veto_burn(id), Err(Error::VetoWindowClosed));
+        assert_eq!(erc20.finalize_burn(id), Ok(()));
+        assert_eq!(erc20.total_supply(), 9000);
+        assert_eq!(erc20.balance_of(accounts.frank), 0);
+        assert_eq!(erc20.balance_of(accounts.alice), 7000);
+      }
+
+      #[ink::test]
+      fn soulbound_blocks_transfers_only() {
+        let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+        ink::env::test::set_callee::<ink::env::DefaultEnvironment>(accounts.django);
+        let mut erc20 = Erc20::new(10000);
+        assert_eq!(erc20.approve(accounts.bob, 100), Ok(()));
+        assert_eq!(erc20.set_transferable(false), Ok(()));
+
+        assert_eq!(erc20.transfer(accounts.bob, 1), Err(Error::NonTransferable));
+        assert_eq!(erc20.transfer_with_memo(accounts.bob, 1, Vec::new()), Err(Error::NonTransferable));
+        ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+        assert_eq!(erc20.transfer_from(accounts.alice, accounts.bob, 1), Err(Error::NonTransferable));
+
+        ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+        assert_eq!(erc20.mint(accounts.bob, 50), Ok(()));
+        ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+        assert_eq!(erc20.burn(20), Ok(()));
+        assert_eq!(erc20.balance_of(accounts.bob), 30);
+        assert_eq!(erc20.total_supply(), 10030);
+
+        ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+        assert_eq!(erc20.set_transferable(true), Ok(()));
+        assert_eq!(erc20.transfer(accounts.bob, 1), Ok(()));
+      }
+
+      #[ink::test]
+      fn soulbound_leaves_escrow_exits_open() {
+        let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+        ink::env::test::set_callee::<ink::env::DefaultEnvironment>(accounts.django);
+        let mut erc20 = Erc20::new(10000);
+        let delayed = erc20.transfer_delayed(accounts.bob, 100, 60).expect("escrow failed");
+        let claimable = erc20.transfer_claimable(accounts.bob, 50, 60_000).expect("escrow failed");
+        assert_eq!(erc20.set_transferable(false), Ok(()));
+
+        assert_eq!(erc20.cancel_transfer(delayed), Ok(()));
+        ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+        assert_eq!(erc20.claim(claimable), Ok(()));
+        assert_eq!(erc20.balance_of(accounts.alice), 9950);
+        assert_eq!(erc20.balance_of(accounts.bob), 50);
+        assert_eq!(erc20.balance_of(accounts.django), 0);
+      }
+
+      #[ink::test]
+      fn registration_gates_recipients() {
+        let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+        ink::env::test::set_callee::<ink::env::DefaultEnvironment>(accounts.django);
+        let mut erc20 = Erc20::new(10000);
+        assert_eq!(erc20.set_require_registration(true), Ok(()));
+        assert_eq!(erc20.register(accounts.bob), Ok(()));
+
+        assert_eq!(erc20.transfer(accounts.bob, 10), Ok(()));
+        assert_eq!(erc20.transfer(accounts.charlie, 10), Err(Error::RecipientNotRegistered));
+        assert_eq!(erc20.transfer_delayed(accounts.charlie, 10, 0), Err(Error::RecipientNotRegistered));
+        let id = erc20.transfer_delayed(accounts.bob, 10, 0).expect("escrow failed");
+        assert_eq!(erc20.claim_transfer(id), Ok(()));
+        assert_eq!(erc20.balance_of(accounts.bob), 20);
+
+        // Refunds go back to the unregistered sender.
+        let id = erc20.transfer_delayed(accounts.bob, 10, 60).expect("escrow failed");
+        assert_eq!(erc20.cancel_transfer(id), Ok(()));
+        assert_eq!(erc20.balance_of(accounts.alice), 10000 - 20);
+
+        assert_eq!(erc20.deregister(accounts.bob), Ok(()));
+        assert!(!erc20.is_registered(accounts.bob));
+        assert_eq!(erc20.transfer(accounts.bob, 10), Err(Error::RecipientNotRegistered));
+
+        ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+        assert_eq!(erc20.register(accounts.bob), Err(Error::NotOwner));
+      }
+
+      #[ink::test]
+      fn min_mint_interval_throttles_mints() {
+        let mut erc20 = Erc20::new(10000);
+        let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+        assert_eq!(erc20.set_min_mint_interval(Some(3600)), Ok(()));
+
+        ink::env::test::set_block_timestamp::<ink::env::DefaultEnvironment>(1_000_000);
+        assert_eq!(erc20.mint(accounts.bob, 1), Ok(()));
+        assert_eq!(erc20.mint(accounts.bob, 1), Err(Error::MintTooSoon));
+
+        ink::env::test::set_block_timestamp::<ink::env::DefaultEnvironment>(1_000_000 + 3_599_999);
+        assert_eq!(erc20.mint(accounts.bob, 1), Err(Error::MintTooSoon));
+
+        ink::env::test::set_block_timestamp::<ink::env::DefaultEnvironment>(1_000_000 + 3_600_000);
+        assert_eq!(erc20.mint(accounts.bob, 1), Ok(()));
+        assert_eq!(erc20.balance_of(accounts.bob), 2);
+      }
+
+      #[ink::test]
+      fn queued_mints_execute_after_delay() {
+        let mut erc20 = Erc20::new(10000);
+        let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+        assert_eq!(erc20.set_mint_queue_delay(60), Ok(()));
+        assert_eq!(erc20.queue_mint(accounts.bob, 100), Ok(()));
+
+        ink::env::test::set_block_timestamp::<ink::env::DefaultEnvironment>(30_000);
+        assert_eq!(erc20.queue_mint(accounts.charlie, 200), Ok(()));
+        assert_eq!(erc20.pending_mints(), vec![
+          PendingMint { to: accounts.bob, value: 100, ready_at: 60_000 },
+          PendingMint { to: accounts.charlie, value: 200, ready_at: 90_000 },
+        ]);
+
+        ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.eve);
+        assert_eq!(erc20.queue_mint(accounts.eve, 1), Err(Error::NotOwner));
+        assert_eq!(erc20.execute_queued_mints(), Ok(0));
+
+        ink::env::test::set_block_timestamp::<ink::env::DefaultEnvironment>(60_000);
+        assert_eq!(erc20.execute_queued_mints(), Ok(1));
+        assert_eq!(erc20.balance_of(accounts.bob), 100);
+        assert_eq!(erc20.pending_mints().len(), 1);
+
+        ink::env::test::set_block_timestamp::<ink::env::DefaultEnvironment>(90_000);
+        assert_eq!(erc20.execute_queued_mints(), Ok(1));
+        assert_eq!(erc20.balance_of(accounts.charlie), 200);
+        assert_eq!(erc20.pending_mints(), Vec::new());
+        assert_eq!(erc20.total_supply(), 10300);
+      }
+
+      #[ink::test]
+      fn queued_mints_follow_interval_and_cap() {
+        let mut erc20 = Erc20::new(10000);
+        let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+        assert_eq!(erc20.set_min_mint_interval(Some(60)), Ok(()));
+        assert_eq!(erc20.mint(accounts.bob, 1), Ok(()));
+        assert_eq!(erc20.queue_mint(accounts.bob, 10), Ok(()));
+        assert_eq!(erc20.queue_mint(accounts.bob, 10), Ok(()));
+        assert_eq!(erc20.execute_queued_mints(), Ok(0));
+
+        ink::env::test::set_block_timestamp::<ink::env::DefaultEnvironment>(60_000);
+        assert_eq!(erc20.execute_queued_mints(), Ok(1));
+        assert_eq!(erc20.execute_queued_mints(), Ok(0));
+        assert_eq!(erc20.balance_of(accounts.bob), 11);
+
+        // Over the cap the entry stays queued until the owner cancels it.
+        assert_eq!(erc20.set_supply_cap(Some(10011), 0), Ok(()));
+        ink::env::test::set_block_timestamp::<ink::env::DefaultEnvironment>(120_000);
+        assert_eq!(erc20.execute_queued_mints(), Ok(0));
+        assert_eq!(erc20.pending_mints().len(), 1);
+        assert_eq!(erc20.cancel_queued_mint(1), Err(Error::QueuedMintNotFound));
+        assert_eq!(erc20.cancel_queued_mint(0), Ok(()));
+        assert_eq!(erc20.pending_mints(), Vec::new());
+      }
+
+      #[ink::test]
+      fn emission_schedule_respects_mint_approval_threshold() {
+        let mut erc20 = Erc20::new(10000);
+        let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+        assert_eq!(erc20.set_mint_approval_threshold(Some(1000)), Ok(()));
+
+        assert_eq!(erc20.set_emission_schedule(accounts.bob, 10, 101_000), Err(Error::MintRequiresApproval));
+        assert_eq!(erc20.remaining_mintable(), 0);
+        assert_eq!(erc20.set_emission_schedule(accounts.bob, 10, 100_000), Ok(()));
+        assert_eq!(erc20.remaining_mintable(), 1000);
+      }
+
+      #[ink::test]
+      fn large_mint_needs_two_approvers() {
+        let mut erc20 = Erc20::new(10000);
+        let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+        assert_eq!(erc20.set_mint_approval_threshold(Some(1000)), Ok(()));
+        assert_eq!(erc20.set_approvers(vec![accounts.bob, accounts.charlie, accounts.django]), Ok(()));
+
+        assert_eq!(erc20.mint(accounts.eve, 1000), Ok(()));
+        assert_eq!(erc20.mint(accounts.eve, 1001), Err(Error::MintRequiresApproval));
+
+        let id = erc20.propose_mint(accounts.eve, 5000).expect("propose failed");
+        assert_eq!(erc20.approve_mint(id), Err(Error::NotApprover));
+
+        ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+        assert_eq!(erc20.approve_mint(id), Ok(()));
+        assert_eq!(erc20.approve_mint(id), Err(Error::AlreadyApproved));
+
+        ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+        assert_eq!(erc20.execute_mint(id), Err(Error::InsufficientApprovals));
+
+        ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+        assert_eq!(erc20.approve_mint(id), Ok(()));
+
+        ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+        assert_eq!(erc20.execute_mint(id), Ok(()));
+        assert_eq!(erc20.balance_of(accounts.eve), 6000);
+        assert_eq!(erc20.total_supply(), 16000);
+        assert_eq!(erc20.execute_mint(id), Err(Error::ProposalNotFound));
+      }
+
+      #[ink::test]
+      fn removed_approver_no_longer_counts() {
+        let mut erc20 = Erc20::new(10000);
+        let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+        assert_eq!(erc20.set_approvers(vec![accounts.bob, accounts.charlie]), Ok(()));
+        let id = erc20.propose_mint(accounts.eve, 5000).expect("propose failed");
+
+        ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+        assert_eq!(erc20.approve_mint(id), Ok(()));
+        ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+        assert_eq!(erc20.approve_mint(id), Ok(()));
+
+        ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+        assert_eq!(erc20.set_approvers(vec![accounts.bob]), Ok(()));
+        assert_eq!(erc20.execute_mint(id), Err(Error::InsufficientApprovals));
+      }
+
+      #[ink::test]
+      fn projected_supply_follows_emission() {
+        let mut erc20 = Erc20::new(10000);
+        let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+        assert_eq!(erc20.projected_supply(50_000), 10000);
+        assert_eq!(erc20.set_emission_schedule(accounts.bob, 10, 100_000), Ok(()));
+
+        assert_eq!(erc20.projected_supply(0), 10000);
+        assert_eq!(erc20.projected_supply(25_000), 10250);
+        assert_eq!(erc20.projected_supply(99_999), 10990);
+        assert_eq!(erc20.projected_supply(500_000), 11000);
+
+        ink::env::test::set_block_timestamp::<ink::env::DefaultEnvironment>(25_000);
+        assert_eq!(erc20.emit_tokens(), Ok(250));
+        assert_eq!(erc20.projected_supply(500_000), 11000);
+      }
+
+      #[ink::test]
+      fn remaining_mintable_follows_emission() {
+        let mut erc20 = Erc20::new(10000);
+        let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+        assert_eq!(erc20.remaining_mintable(), 0);
+        assert_eq!(erc20.set_emission_schedule(accounts.bob, 10, 100_000), Ok(()));
+        assert_eq!(erc20.remaining_mintable(), 1000);
+
+        ink::env::test::set_block_timestamp::<ink::env::DefaultEnvironment>(40_000);
+        assert_eq!(erc20.emit_tokens(), Ok(400));
+        assert_eq!(erc20.remaining_mintable(), 600);
+
+        ink::env::test::set_block_timestamp::<ink::env::DefaultEnvironment>(100_000);
+        assert_eq!(erc20.emit_tokens(), Ok(600));
+        assert_eq!(erc20.remaining_mintable(), 0);
+      }
+
+      #[ink::test]
+      fn emission_stops_at_the_cap() {
+        let mut erc20 = Erc20::new(10000);
+        let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+        assert_eq!(erc20.set_supply_cap(Some(12000), 0), Ok(()));
+        assert_eq!(erc20.remaining_mintable(), 2000);
+        assert_eq!(erc20.set_emission_schedule(accounts.bob, 10, 1_000_000), Ok(()));
+        assert_eq!(erc20.projected_supply(1_000_000), 12000);
+
+        ink::env::test::set_block_timestamp::<ink::env::DefaultEnvironment>(300_000);
+        assert_eq!(erc20.set_emission_schedule(accounts.bob, 0, 0), Ok(()));
+        assert_eq!(erc20.total_supply(), 12000);
+        assert_eq!(erc20.balance_of(accounts.bob), 2000);
+      }
+
+      #[ink::test]
+      fn remaining_mintable_hits_zero_at_the_cap() {
+        let mut erc20 = Erc20::new(10000);
+        let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+        assert_eq!(erc20.set_emission_schedule(accounts.bob, 10, 100_000), Ok(()));
+        assert_eq!(erc20.set_supply_cap(Some(10300), 0), Ok(()));
+        assert_eq!(erc20.remaining_mintable(), 300);
+
+        assert_eq!(erc20.mint(accounts.bob, 300), Ok(()));
+        assert_eq!(erc20.remaining_mintable(), 0);
+      }
+
+      #[ink::test]
+      fn batch_transfer_budgeted_works() {
+        let mut erc20 = Erc20::new(10000);
+        let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+        let recipients = vec![accounts.bob, accounts.charlie, accounts.django];
+
+        let res = erc20.batch_transfer_budgeted(recipients.clone(), vec![10, 20, 30], 60);
+        assert_eq!(res, Ok(3));
+        assert_eq!(erc20.balance_of(accounts.django), 30);
+
+        let res = erc20.batch_transfer_budgeted(recipients.clone(), vec![10, 20, 30], 45);
+        assert_eq!(res, Ok(2));
+        assert_eq!(erc20.balance_of(accounts.bob), 20);
+        assert_eq!(erc20.balance_of(accounts.charlie), 40);
+        assert_eq!(erc20.balance_of(accounts.django), 30);
+        assert_eq!(erc20.balance_of(accounts.alice), 10000 - 90);
+
+        let res = erc20.batch_transfer_budgeted(recipients.clone(), vec![10], 45);
+        assert_eq!(res, Err(Error::LengthMismatch));
+
+        assert_eq!(erc20.set_memo_required_above(Some(15)), Ok(()));
+        let res = erc20.batch_transfer_budgeted(recipients, vec![10, 20, 30], 60);
+        assert_eq!(res, Err(Error::MemoRequired));
+      }
 
+      #[ink::test]
+      fn memo_required_above_threshold() {
+        let mut erc20 = Erc20::new(10000);
+        let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+        assert_eq!(erc20.set_memo_required_above(Some(1000)), Ok(()));
 
-    #[ink(event)]
-    pub struct Transfer {
-      from: Option<AccountId>,
-      to: Option<AccountId>,
-      value: Balance
-    }
+        assert_eq!(erc20.transfer(accounts.bob, 1001), Err(Error::MemoRequired));
+        assert_eq!(erc20.transfer_with_memo(accounts.bob, 1001, b"invoice-42".to_vec()), Ok(()));
+        assert_eq!(erc20.transfer(accounts.bob, 1000), Ok(()));
+        assert_eq!(erc20.balance_of(accounts.bob), 2001);
 
-    #[ink(event)]
-    pub struct Approve {
-      from: AccountId,
-      to: AccountId,
-      value: Balance,
-    }
+        let emitted_events = ink::env::test::recorded_events().collect::<Vec<_>>();
+        let decode = <Event as scale::Decode>::decode(&mut &emitted_events[2].data[..]).expect("decode error");
+        match decode {
+          Event::TransferMemo(TransferMemo{ from, to, value, memo }) => {
+            assert_eq!(from, accounts.alice);
+            assert_eq!(to, accounts.bob);
+            assert_eq!(value, 1001);
+            assert_eq!(memo, b"invoice-42".to_vec());
+          }
+          _ => panic!("match error"),
+        }
+      }
 
-    impl Erc20 {
-      /// Constructor that initializes the `bool` value to the given `init_value`.
-      #[ink(constructor)]
-      pub fn new(total_supply: Balance) -> Self {
-        let mut balances = Mapping::new();
-        balances.insert(Self::env().caller(), &total_supply);
-        Self::env().emit_event(Transfer{
-          from: None,
-          to: Some(Self::env().caller()),
-          value: total_supply,
-        });
-        Self { total_supply, balances, ..Default::default() }
+      #[ink::test]
+      fn migrate_balance_works() {
+        let mut erc20 = Erc20::new(10000);
+        let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+        assert_eq!(erc20.transfer(accounts.bob, 300), Ok(()));
+
+        ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+        assert_eq!(erc20.approve(accounts.charlie, 50), Ok(()));
+        assert_eq!(erc20.approve(accounts.django, 20), Ok(()));
+
+        ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+        assert_eq!(erc20.migrate_balance(accounts.bob, accounts.eve), Err(Error::NotAllowed));
+
+        ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+        assert_eq!(erc20.migrate_balance(accounts.bob, accounts.eve), Ok(()));
+        assert_eq!(erc20.balance_of(accounts.bob), 0);
+        assert_eq!(erc20.balance_of(accounts.eve), 300);
+        assert_eq!(erc20.allowance(accounts.bob, accounts.charlie), 0);
+        assert_eq!(erc20.allowance(accounts.eve, accounts.charlie), 50);
+        assert_eq!(erc20.allowance(accounts.eve, accounts.django), 20);
+
+        ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.eve);
+        assert_eq!(erc20.migrate_balance(accounts.eve, accounts.frank), Ok(()));
+        assert_eq!(erc20.balance_of(accounts.frank), 300);
+        assert_eq!(erc20.allowance(accounts.frank, accounts.charlie), 50);
       }
 
-      pub fn transfer_helper(&mut self, from: &AccountId, to: &AccountId, value: Balance) -> Result<()> {
-        let balance_from = self.balance_of(*from);
-        let balance_to = self.balance_of(*to);
+      #[ink::test]
+      fn migrate_balance_moves_savings_and_skips_transfer_rules() {
+        let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+        ink::env::test::set_callee::<ink::env::DefaultEnvironment>(accounts.django);
+        let mut erc20 = Erc20::new(10000);
+        assert_eq!(erc20.set_forced_savings(5000, 60), Ok(()));
+        assert_eq!(erc20.transfer(accounts.bob, 1000), Ok(()));
+        let id = erc20.transfer_claimable(accounts.charlie, 100, 3_600_000).expect("escrow failed");
 
-        if value > balance_from {
-          return Err(Error::BalanceTooLow);
-        }
+        assert_eq!(erc20.migrate_balance(accounts.django, accounts.eve), Err(Error::NotAllowed));
+        assert_eq!(erc20.migrate_balance(accounts.bob, accounts.django), Err(Error::NotAllowed));
 
-        self.balances.insert(from, &(balance_from - value));
-        self.balances.insert(to, &(balance_to + value));
+        assert_eq!(erc20.set_pause_level(PauseLevel::Full), Ok(()));
+        assert_eq!(erc20.migrate_balance(accounts.bob, accounts.eve), Ok(()));
+        assert_eq!(erc20.balance_of(accounts.eve), 500);
+        assert_eq!(erc20.savings_of(accounts.eve), 500);
+        assert_eq!(erc20.savings_of(accounts.bob), 0);
 
-        self.env().emit_event(Transfer {
-          from: Some(*from),
-          to: Some(*to),
-          value,
-        });
+        assert_eq!(erc20.set_pause_level(PauseLevel::None), Ok(()));
+        ink::env::test::set_block_timestamp::<ink::env::DefaultEnvironment>(60_000);
+        ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.eve);
+        assert_eq!(erc20.withdraw_savings(), Ok(()));
+        assert_eq!(erc20.balance_of(accounts.eve), 1000);
 
-        Ok(())
+        ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+        assert_eq!(erc20.claim(id), Ok(()));
+        assert_eq!(erc20.balance_of(accounts.django), 0);
       }
-    }
 
-    impl TERC20 for Erc20 {
-      #[ink(message)]
-      fn total_supply(&self) -> Balance {
-        self.total_supply
+      #[ink::test]
+      fn self_migration_keeps_transfer_rules() {
+        let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+        ink::env::test::set_callee::<ink::env::DefaultEnvironment>(accounts.frank);
+        let mut erc20 = Erc20::new(10000);
+        assert_eq!(erc20.set_burn_governance(3000, 60), Ok(()));
+        assert_eq!(erc20.transfer(accounts.bob, 2000), Ok(()));
+        let id = erc20.propose_burn(1000).expect("proposal failed");
+
+        ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+        assert_eq!(erc20.transfer(accounts.charlie, 100), Ok(()));
+        assert_eq!(erc20.veto_burn(id), Ok(()));
+        assert_eq!(erc20.migrate_balance(accounts.bob, accounts.eve), Err(Error::BalanceLocked));
+
+        ink::env::test::set_block_timestamp::<ink::env::DefaultEnvironment>(60_000);
+        ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+        assert_eq!(erc20.set_transferable(false), Ok(()));
+        ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+        assert_eq!(erc20.migrate_balance(accounts.bob, accounts.eve), Err(Error::NonTransferable));
+
+        ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+        assert_eq!(erc20.set_transferable(true), Ok(()));
+        assert_eq!(erc20.set_require_registration(true), Ok(()));
+        ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+        assert_eq!(erc20.migrate_balance(accounts.bob, accounts.eve), Err(Error::RecipientNotRegistered));
+
+        ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+        assert_eq!(erc20.register(accounts.eve), Ok(()));
+        ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+        assert_eq!(erc20.migrate_balance(accounts.bob, accounts.eve), Ok(()));
+        assert_eq!(erc20.balance_of(accounts.eve), 1900);
+        assert_eq!(erc20.transfer_stats(accounts.eve).0, 1);
+        assert_eq!(erc20.transfer_stats(accounts.bob).0, 0);
       }
 
-      #[ink(message)]
-      fn balance_of(&self, who: AccountId) -> Balance {
-        self.balances.get(&who).unwrap_or_default()
+      #[ink::test]
+      fn total_allowance_to_works() {
+        let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+        ink::env::test::set_callee::<ink::env::DefaultEnvironment>(accounts.frank);
+        let mut erc20 = Erc20::new(10000);
+        assert_eq!(erc20.transfer(accounts.bob, 100), Ok(()));
+
+        assert_eq!(erc20.approve(accounts.charlie, 50), Ok(()));
+        ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+        assert_eq!(erc20.approve(accounts.charlie, 30), Ok(()));
+        assert_eq!(erc20.total_allowance_to(accounts.charlie), 80);
+
+        ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+        assert_eq!(erc20.transfer_from(accounts.alice, accounts.django, 20), Ok(()));
+        assert_eq!(erc20.total_allowance_to(accounts.charlie), 60);
+
+        ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+        assert_eq!(erc20.approve(accounts.charlie, 0), Ok(()));
+        assert_eq!(erc20.total_allowance_to(accounts.charlie), 30);
+
+        ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+        assert_eq!(erc20.migrate_balance(accounts.alice, accounts.eve), Ok(()));
+        assert_eq!(erc20.total_allowance_to(accounts.charlie), 30);
       }
 
-      #[ink(message)]
-      fn approve(&mut self, to: AccountId, value: Balance) -> Result<()> { // 允许谁动用多少资金
-        let sender = self.env().caller();
-        self.allowances.insert(&(sender, to), &value); // 允许to调用sender的value数字的金额
-        
-        self.env().emit_event(Approve {
-          from: sender,
-          to,
-          value
-        });
+      #[ink::test]
+      fn total_allowance_to_drops_expired_allowance_on_reapprove() {
+        let mut erc20 = Erc20::new(10000);
+        let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+        assert_eq!(erc20.set_default_allowance_ttl(Some(60)), Ok(()));
+        assert_eq!(erc20.approve(accounts.bob, 100), Ok(()));
 
-        Ok(())
+        ink::env::test::set_block_timestamp::<ink::env::DefaultEnvironment>(61_000);
+        assert_eq!(erc20.allowance(accounts.alice, accounts.bob), 0);
+        assert_eq!(erc20.approve(accounts.bob, 50), Ok(()));
+        assert_eq!(erc20.total_allowance_to(accounts.bob), 50);
       }
 
-      #[ink(message)]
-      fn transfer(&mut self, to: AccountId, value: Balance) -> Result<()> {
-        let sender = self.env().caller();
-        
-        return self.transfer_helper(&sender, &to, value);
+      #[ink::test]
+      fn set_allowances_works() {
+        let mut erc20 = Erc20::new(10000);
+        let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+        assert_eq!(erc20.approve(accounts.bob, 500), Ok(()));
+
+        let spenders = vec![accounts.bob, accounts.charlie, accounts.django];
+        assert_eq!(erc20.set_allowances(spenders.clone(), vec![10, 20, 0]), Ok(()));
+        assert_eq!(erc20.allowance(accounts.alice, accounts.bob), 10);
+        assert_eq!(erc20.allowance(accounts.alice, accounts.charlie), 20);
+        assert_eq!(erc20.allowance(accounts.alice, accounts.django), 0);
+        // One `Approve` per entry, after the mint and the first approval.
+        assert_eq!(ink::env::test::recorded_events().count(), 2 + 3);
+
+        assert_eq!(erc20.set_allowances(spenders, vec![1, 2]), Err(Error::LengthMismatch));
       }
 
-      #[ink(message)]
-      fn transfer_from(&mut self, from: AccountId, to: AccountId, value: Balance) -> Result<()> {
-        let sender = self.env().caller();
-        let mut allowances = self.allowances.get(&(from, sender)).unwrap_or_default(); // 获取允许sender调用from的金额
+      #[ink::test]
+      fn zero_allowances_are_removed() {
+        let mut erc20 = Erc20::new(10000);
+        let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+        assert_eq!(erc20.set_default_allowance_ttl(Some(3600)), Ok(()));
+        assert_eq!(erc20.approve(accounts.bob, 100), Ok(()));
+        assert_eq!(erc20.approve(accounts.charlie, 50), Ok(()));
+        assert_eq!(erc20.spenders_of(accounts.alice), vec![accounts.bob, accounts.charlie]);
 
-        if allowances < value {
-          return Err(Error::AllowancesTooLow);
-        }
+        ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+        assert_eq!(erc20.transfer_from(accounts.alice, accounts.bob, 100), Ok(()));
+        assert!(!erc20.allowances.contains((accounts.alice, accounts.bob)));
+        assert_eq!(erc20.allowance_expiry(accounts.alice, accounts.bob), None);
+        assert_eq!(erc20.allowance(accounts.alice, accounts.bob), 0);
+        assert_eq!(erc20.spenders_of(accounts.alice), vec![accounts.charlie]);
+
+        ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+        assert_eq!(erc20.approve(accounts.charlie, 0), Ok(()));
+        assert!(!erc20.allowances.contains((accounts.alice, accounts.charlie)));
+        assert_eq!(erc20.spenders_of(accounts.alice), Vec::<AccountId>::new());
+        assert_eq!(erc20.total_allowance_to(accounts.charlie), 0);
+      }
+
+      #[ink::test]
+      fn allowances_batch_works() {
+        let mut erc20 = Erc20::new(10000);
+        let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+        assert_eq!(erc20.approve(accounts.bob, 10), Ok(()));
+        assert_eq!(erc20.approve(accounts.charlie, 20), Ok(()));
 
-        self.allowances.insert(&(from, sender), &(allowances - value));
+        let pairs = vec![
+          (accounts.alice, accounts.charlie),
+          (accounts.bob, accounts.alice),
+          (accounts.alice, accounts.bob),
+        ];
+        assert_eq!(erc20.allowances_batch(pairs), Ok(vec![20, 0, 10]));
 
-        return self.transfer_helper(&from, &to, value);
+        let pairs = vec![(accounts.alice, accounts.bob); MAX_BATCH_QUERY + 1];
+        assert_eq!(erc20.allowances_batch(pairs), Err(Error::BatchTooLarge));
       }
 
-      
-    }
+      #[ink::test]
+      fn percent_allowance_tracks_balance() {
+        let mut erc20 = Erc20::new(10000);
+        let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+        assert_eq!(erc20.approve_percent(accounts.bob, 10001), Err(Error::InvalidBasisPoints));
+        assert_eq!(erc20.approve(accounts.bob, 5), Ok(()));
+        assert_eq!(erc20.approve_percent(accounts.bob, 2500), Ok(()));
+        assert_eq!(erc20.allowance(accounts.alice, accounts.bob), 2500);
+        assert_eq!(erc20.total_allowance_to(accounts.bob), 0);
+
+        assert_eq!(erc20.transfer(accounts.charlie, 6000), Ok(()));
+        assert_eq!(erc20.allowance(accounts.alice, accounts.bob), 1000);
+
+        // Spending does not decrement a percentage allowance, only the balance it follows.
+        ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+        assert_eq!(erc20.transfer_from(accounts.alice, accounts.bob, 1001), Err(Error::AllowancesTooLow));
+        assert_eq!(erc20.transfer_from(accounts.alice, accounts.bob, 1000), Ok(()));
+        assert_eq!(erc20.allowance(accounts.alice, accounts.bob), 750);
+
+        // A fixed approval replaces the percentage one.
+        ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+        assert_eq!(erc20.approve(accounts.bob, 10), Ok(()));
+        assert_eq!(erc20.allowance(accounts.alice, accounts.bob), 10);
+      }
 
-    /// Unit tests in Rust are normally defined within such a `#[cfg(test)]`
-    /// module and test functions are marked with a `#[test]` attribute.
-    /// The below code is technically just normal Rust code.
-    #[cfg(test)]
-    mod tests {
-      /// Imports all the definitions from the outer scope so we can use them here.
-      use super::*;
-      type Event = <Erc20 as ::ink::reflect::ContractEventBase>::Type;
-        /// We test if the default constructor does its job.
       #[ink::test]
-      fn constructor_works() {
-        let erc20 = Erc20::new(10000);
+      fn default_allowance_ttl_expires_approvals() {
+        let mut erc20 = Erc20::new(10000);
         let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
-        assert_eq!(erc20.total_supply(), 10000);
-        assert_eq!(erc20.balance_of(accounts.alice), 10000);
+        assert_eq!(erc20.approve(accounts.charlie, 100), Ok(()));
+        assert_eq!(erc20.set_default_allowance_ttl(Some(60)), Ok(()));
+        assert_eq!(erc20.approve(accounts.bob, 100), Ok(()));
+        assert_eq!(erc20.allowance_expiry(accounts.alice, accounts.bob), Some(60_000));
+        assert_eq!(erc20.allowance_expiry(accounts.alice, accounts.charlie), None);
 
-        let emitted_events = ink::env::test::recorded_events().collect::<Vec<_>>();
+        ink::env::test::set_block_timestamp::<ink::env::DefaultEnvironment>(59_999);
+        ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+        assert_eq!(erc20.transfer_from(accounts.alice, accounts.bob, 40), Ok(()));
+        // Spending does not refresh the expiry.
+        assert_eq!(erc20.allowance_expiry(accounts.alice, accounts.bob), Some(60_000));
 
-        let event = &emitted_events[0];
+        ink::env::test::set_block_timestamp::<ink::env::DefaultEnvironment>(60_000);
+        assert_eq!(erc20.allowance(accounts.alice, accounts.bob), 0);
+        assert_eq!(erc20.transfer_from(accounts.alice, accounts.bob, 1), Err(Error::AllowancesTooLow));
+        assert_eq!(erc20.allowance(accounts.alice, accounts.charlie), 100);
+      }
 
-        let decode = <Event as scale::Decode>::decode(&mut &event.data[..]).expect("decode error");
+      #[ink::test]
+      fn allowance_updated_at_tracks_changes() {
+        let mut erc20 = Erc20::new(10000);
+        let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+        assert_eq!(erc20.allowance_updated_at(accounts.alice, accounts.bob), None);
 
-        match decode {
-          Event::Transfer(Transfer{ from, to, value }) => {
-            assert!(from.is_none(), "mint from error");
-            assert_eq!(to, Some(accounts.alice), "mint to error");
-            assert_eq!(value, 10000, "mint value error");
-          }
-          _ => panic!("match error"),
-        }
+        ink::env::test::set_block_timestamp::<ink::env::DefaultEnvironment>(1_000);
+        assert_eq!(erc20.approve(accounts.bob, 100), Ok(()));
+        assert_eq!(erc20.allowance_updated_at(accounts.alice, accounts.bob), Some(1_000));
+
+        ink::env::test::set_block_timestamp::<ink::env::DefaultEnvironment>(5_000);
+        ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+        assert_eq!(erc20.transfer_from(accounts.alice, accounts.bob, 10), Ok(()));
+        assert_eq!(erc20.allowance_updated_at(accounts.alice, accounts.bob), Some(5_000));
+
+        ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+        assert_eq!(erc20.approve_recurring(accounts.bob, 100, 60), Ok(()));
+        ink::env::test::set_block_timestamp::<ink::env::DefaultEnvironment>(9_000);
+        ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+        assert_eq!(erc20.transfer_from(accounts.alice, accounts.bob, 10), Ok(()));
+        assert_eq!(erc20.allowance_updated_at(accounts.alice, accounts.bob), Some(9_000));
       }
 
       #[ink::test]
-      fn transfer_works() {
+      fn holder_set_tracks_balances() {
         let mut erc20 = Erc20::new(10000);
         let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+        assert_eq!(erc20.holder_count(), 1);
 
-        let res = erc20.transfer(accounts.bob, 12);
-        assert!(res.is_ok());
-        assert_eq!(erc20.balance_of(accounts.alice), 10000 - 12);
-        assert_eq!(erc20.balance_of(accounts.bob), 12);
+        assert_eq!(erc20.transfer(accounts.bob, 10), Ok(()));
+        assert_eq!(erc20.transfer(accounts.charlie, 10), Ok(()));
+        assert_eq!(erc20.holder_count(), 3);
+
+        ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+        assert_eq!(erc20.transfer(accounts.charlie, 10), Ok(()));
+        assert_eq!(erc20.holder_count(), 2);
+        assert_eq!(erc20.holders_except(&accounts.alice), vec![(accounts.charlie, 20)]);
       }
 
       #[ink::test]
-      fn invalid_transfer_should_fail() {
+      fn transfer_creates_holder_works() {
+        let mut erc20 = Erc20::new(10000);
+        let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+        assert!(erc20.transfer_creates_holder(accounts.bob));
+        assert!(!erc20.transfer_creates_holder(accounts.alice));
+
+        assert_eq!(erc20.transfer(accounts.bob, 1), Ok(()));
+        assert!(!erc20.transfer_creates_holder(accounts.bob));
+      }
+
+      #[ink::test]
+      fn has_ever_held_survives_full_drain() {
         let mut erc20 = Erc20::new(10000);
         let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+        assert!(erc20.has_ever_held(accounts.alice));
+        assert!(!erc20.has_ever_held(accounts.bob));
+
+        assert_eq!(erc20.transfer(accounts.bob, 10), Ok(()));
+        assert!(erc20.has_ever_held(accounts.bob));
+
         ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+        assert_eq!(erc20.transfer(accounts.charlie, 10), Ok(()));
+        assert_eq!(erc20.balance_of(accounts.bob), 0);
+        assert!(erc20.has_ever_held(accounts.bob));
+      }
 
-        let res = erc20.transfer(accounts.charlie, 12);
-        assert!(res.is_err());
-        assert_eq!(res, Err(Error::BalanceTooLow));
+      #[ink::test]
+      fn top_holders_follow_transfers() {
+        let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+        ink::env::test::set_callee::<ink::env::DefaultEnvironment>(accounts.frank);
+        let mut erc20 = Erc20::new(1000);
+        assert_eq!(erc20.transfer(accounts.bob, 300), Ok(()));
+        assert_eq!(erc20.transfer(accounts.charlie, 200), Ok(()));
+        assert_eq!(erc20.top_holders(2), vec![(accounts.alice, 500), (accounts.bob, 300)]);
+
+        ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+        assert_eq!(erc20.transfer(accounts.charlie, 450), Ok(()));
+        assert_eq!(erc20.top_holders(10), vec![
+          (accounts.charlie, 650),
+          (accounts.bob, 300),
+          (accounts.alice, 50),
+        ]);
+
+        ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+        assert_eq!(erc20.transfer(accounts.django, 650), Ok(()));
+        assert_eq!(erc20.top_holders(u32::MAX), vec![
+          (accounts.django, 650),
+          (accounts.bob, 300),
+          (accounts.alice, 50),
+        ]);
+      }
+
+      #[ink::test]
+      fn top_holders_refill_from_unlisted_holders() {
+        let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+        ink::env::test::set_callee::<ink::env::DefaultEnvironment>(accounts.frank);
+        let mut erc20 = Erc20::new(100_000);
+        // Alice plus these accounts fill every ranked slot.
+        for i in 0..MAX_TOP_HOLDERS as u8 - 1 {
+          assert_eq!(erc20.transfer(AccountId::from([0x10 + i; 32]), 100 + i as Balance), Ok(()));
+        }
+        // The list is full, so a smaller newcomer is not ranked yet.
+        assert_eq!(erc20.transfer(accounts.bob, 99), Ok(()));
+        assert!(!erc20.top_holders(u32::MAX).iter().any(|(holder, _)| *holder == accounts.bob));
+
+        // A ranked holder dropping below bob frees a slot that bob takes.
+        ink::env::test::set_caller::<ink::env::DefaultEnvironment>(AccountId::from([0x10; 32]));
+        assert_eq!(erc20.transfer(accounts.charlie, 50), Ok(()));
+        let top = erc20.top_holders(u32::MAX);
+        assert_eq!(top.len(), MAX_TOP_HOLDERS);
+        assert_eq!(top[MAX_TOP_HOLDERS - 1], (accounts.bob, 99));
+        assert!(!top.iter().any(|(holder, _)| *holder == AccountId::from([0x10; 32])));
+      }
+
+      #[ink::test]
+      fn top_holders_skip_the_contract_account() {
+        let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+        ink::env::test::set_callee::<ink::env::DefaultEnvironment>(accounts.django);
+        let mut erc20 = Erc20::new(1000);
+        assert_eq!(erc20.transfer(accounts.bob, 150), Ok(()));
+        // Escrow parks 800 on the contract account, more than anyone else holds.
+        assert!(erc20.transfer_delayed(accounts.eve, 800, 60).is_ok());
+        assert_eq!(erc20.balance_of(accounts.django), 800);
+        assert_eq!(erc20.top_holders(u32::MAX), vec![(accounts.bob, 150), (accounts.alice, 50)]);
+      }
+
+      #[ink::test]
+      fn rank_of_ignores_the_contract_account() {
+        let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+        ink::env::test::set_callee::<ink::env::DefaultEnvironment>(accounts.django);
+        let mut erc20 = Erc20::new(1000);
+        assert_eq!(erc20.transfer(accounts.bob, 150), Ok(()));
+        assert!(erc20.transfer_delayed(accounts.eve, 800, 60).is_ok());
+        assert_eq!(erc20.rank_of(accounts.django), None);
+        assert_eq!(erc20.rank_of(accounts.bob), Some(1));
+        assert_eq!(erc20.rank_of(accounts.alice), Some(2));
+      }
+
+      #[ink::test]
+      fn distribute_proportional_pays_out_exactly() {
+        let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+        ink::env::test::set_callee::<ink::env::DefaultEnvironment>(accounts.frank);
+        let mut erc20 = Erc20::new(10000);
+        assert_eq!(erc20.transfer(accounts.bob, 1), Ok(()));
+        assert_eq!(erc20.transfer(accounts.charlie, 2), Ok(()));
+        // Escrowed tokens sit on the contract account and must not take part.
+        assert!(erc20.transfer_delayed(accounts.eve, 100, 60).is_ok());
+
+        // 10 * 1/3 = 3 and 10 * 2/3 = 6; the remaining 1 goes to charlie as the largest holder.
+        assert_eq!(erc20.distribute_proportional(10), Ok(()));
+        assert_eq!(erc20.balance_of(accounts.bob), 1 + 3);
+        assert_eq!(erc20.balance_of(accounts.charlie), 2 + 6 + 1);
+        assert_eq!(erc20.balance_of(accounts.frank), 100);
+        assert_eq!(erc20.balance_of(accounts.alice), 10000 - 3 - 100 - 10);
+      }
+
+      #[ink::test]
+      fn distribute_proportional_remainder_goes_to_sink() {
+        let mut erc20 = Erc20::new(10000);
+        let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+        assert_eq!(erc20.transfer(accounts.bob, 1), Ok(()));
+        assert_eq!(erc20.transfer(accounts.charlie, 2), Ok(()));
+        assert_eq!(erc20.set_distribution_sink(Some(accounts.django)), Ok(()));
+
+        assert_eq!(erc20.distribute_proportional(10), Ok(()));
+        assert_eq!(erc20.balance_of(accounts.bob), 1 + 3);
+        assert_eq!(erc20.balance_of(accounts.charlie), 2 + 6);
+        assert_eq!(erc20.balance_of(accounts.django), 1);
+      }
+
+      #[ink::test]
+      fn scheduled_distribution_waits_for_interval() {
+        let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+        ink::env::test::set_callee::<ink::env::DefaultEnvironment>(accounts.frank);
+        let mut erc20 = Erc20::new(10000);
+        assert_eq!(erc20.transfer(accounts.bob, 1000), Ok(()));
+        assert_eq!(erc20.transfer(accounts.charlie, 3000), Ok(()));
+        assert_eq!(erc20.set_distribution_interval(3600), Ok(()));
+
+        assert_eq!(erc20.deposit_revenue(100), Ok(()));
+        assert_eq!(erc20.pending_revenue(), 100);
+
+        ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.eve);
+        ink::env::test::set_block_timestamp::<ink::env::DefaultEnvironment>(3_599_999);
+        assert_eq!(erc20.trigger_distribution(), Err(Error::DistributionTooSoon));
+
+        // Weights are alice 5900, bob 1000 and charlie 3000 out of 9900.
+        ink::env::test::set_block_timestamp::<ink::env::DefaultEnvironment>(3_600_000);
+        assert_eq!(erc20.trigger_distribution(), Ok(100));
+        assert_eq!(erc20.balance_of(accounts.alice), 5900 + 59 + 1);
+        assert_eq!(erc20.balance_of(accounts.bob), 1000 + 10);
+        assert_eq!(erc20.balance_of(accounts.charlie), 3000 + 30);
+        assert_eq!(erc20.balance_of(accounts.frank), 0);
+        assert_eq!(erc20.pending_revenue(), 0);
+
+        assert_eq!(erc20.trigger_distribution(), Err(Error::DistributionTooSoon));
+      }
+
+      #[ink::test]
+      fn distribute_proportional_without_recipients_fails() {
+        let mut erc20 = Erc20::new(10000);
+        assert_eq!(erc20.distribute_proportional(10), Err(Error::NoRecipients));
       }
     }
 
@@ -216,6 +3822,63 @@ mod erc20 {
           Ok(())
           // 报错，提示：erc20::e2e_tests::default_works' panicked at 'We should find a port before the reader ends'
         }
+
+        #[ink_e2e::test]
+        async fn transfer_with_tip_works(mut client: ink_e2e::Client<C, E>) -> E2EResult<()> {
+          let constructor = Erc20Ref::new(1000);
+          let contract_account_id = client
+              .instantiate("erc20", &ink_e2e::alice(), constructor, 0, None)
+              .await
+              .expect("instantiate failed")
+              .account_id;
+          let bob_acc_id = ink_e2e::account_id(ink_e2e::AccountKeyring::Bob);
+          let native_before = client.balance(bob_acc_id.clone()).await.expect("balance failed");
+
+          let tip = 1_000_000_000;
+          let transfer_message = build_message::<Erc20Ref>(contract_account_id.clone()).call(|erc20| erc20.transfer_with_tip(bob_acc_id, 10));
+          client.call(&ink_e2e::alice(), transfer_message, tip, None).await.expect("transfer_with_tip failed");
+
+          let balance_of_message = build_message::<Erc20Ref>(contract_account_id.clone()).call(|erc20| erc20.balance_of(bob_acc_id));
+          let balance_of_bob = client.call_dry_run(&ink_e2e::alice(), &balance_of_message, 0, None).await;
+          assert_eq!(balance_of_bob.return_value(), 10);
+
+          let native_after = client.balance(bob_acc_id.clone()).await.expect("balance failed");
+          assert_eq!(native_after, native_before + tip);
+
+          Ok(())
+        }
+
+        #[ink_e2e::test(additional_contracts = "gate_stub/Cargo.toml")]
+        async fn gate_oracle_blocks_transfers(mut client: ink_e2e::Client<C, E>) -> E2EResult<()> {
+          use gate_stub::GateStubRef;
+
+          let gate_account_id = client
+              .instantiate("gate_stub", &ink_e2e::alice(), GateStubRef::new(true), 0, None)
+              .await
+              .expect("instantiate gate failed")
+              .account_id;
+          let contract_account_id = client
+              .instantiate("erc20", &ink_e2e::alice(), Erc20Ref::new(1000), 0, None)
+              .await
+              .expect("instantiate failed")
+              .account_id;
+          let bob_acc_id = ink_e2e::account_id(ink_e2e::AccountKeyring::Bob);
+
+          let set_gate_message = build_message::<Erc20Ref>(contract_account_id.clone()).call(|erc20| erc20.set_gate_oracle(Some(gate_account_id.clone())));
+          client.call(&ink_e2e::alice(), set_gate_message, 0, None).await.expect("set_gate_oracle failed");
+
+          let transfer_message = build_message::<Erc20Ref>(contract_account_id.clone()).call(|erc20| erc20.transfer(bob_acc_id, 10));
+          let open = client.call_dry_run(&ink_e2e::alice(), &transfer_message, 0, None).await;
+          assert_eq!(open.return_value(), Ok(()));
+
+          let close_message = build_message::<GateStubRef>(gate_account_id.clone()).call(|gate| gate.set_allowed(false));
+          client.call(&ink_e2e::alice(), close_message, 0, None).await.expect("set_allowed failed");
+
+          let closed = client.call_dry_run(&ink_e2e::alice(), &transfer_message, 0, None).await;
+          assert_eq!(closed.return_value(), Err(Error::GateClosed));
+
+          Ok(())
+        }
         
     }
 }
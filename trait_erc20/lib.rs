@@ -10,7 +10,50 @@ type Balance = <DefaultEnvironment as ::ink::env::Environment>::Balance;
 #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
 pub enum Error {
   BalanceTooLow,
-  AllowancesTooLow
+  AllowancesTooLow,
+  NotOwner,
+  AllowanceCeilingExceeded,
+  DelayedTransferNotFound,
+  NotAllowed,
+  TransferStillPending,
+  CancelWindowElapsed,
+  LengthMismatch,
+  MemoRequired,
+  NoRecipients,
+  Overflow,
+  BatchTooLarge,
+  Paused,
+  InvalidBasisPoints,
+  MintRequiresApproval,
+  ProposalNotFound,
+  NotApprover,
+  AlreadyApproved,
+  InsufficientApprovals,
+  ClaimableTransferNotFound,
+  ClaimExpired,
+  NotExpired,
+  MintTooSoon,
+  NonTransferable,
+  RecipientNotRegistered,
+  DistributionTooSoon,
+  BurnProposalNotFound,
+  BurnStillPending,
+  VetoWindowClosed,
+  AlreadyVetoed,
+  InvalidPeriod,
+  TipTransferFailed,
+  SavingsLocked,
+  GateClosed,
+  NoPendingApproval,
+  TransferQuotaExceeded,
+  CapExceeded,
+  InvalidSignature,
+  InvalidNonce,
+  SignatureExpired,
+  InvalidQuorum,
+  BalanceLocked,
+  ApprovalTooLarge,
+  QueuedMintNotFound,
 }
 
 pub type Result<T> = core::result::Result<T, Error>;
@@ -25,6 +68,9 @@ pub trait TERC20 {
   #[ink(message)]
   fn total_supply(&self) -> Balance;
 
+  #[ink(message)]
+  fn allowance(&self, owner: AccountId, spender: AccountId) -> Balance;
+
   #[ink(message)]
   fn approve(&mut self, to: AccountId, value: Balance) -> Result<()>;
 
@@ -33,4 +79,11 @@ pub trait TERC20 {
 
   #[ink(message)]
   fn transfer_from(&mut self, from: AccountId, to: AccountId, value: Balance) -> Result<()>;
-}
\ No newline at end of file
+}
+
+/// Queried by the token before every transfer once it is configured as the gate oracle.
+#[ink::trait_definition]
+pub trait TransferGate {
+  #[ink(message)]
+  fn is_allowed(&self) -> bool;
+}